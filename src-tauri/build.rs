@@ -1,3 +1,64 @@
+/// Commands the frontend can invoke. Declaring them gives each one
+/// `allow-<command>` / `deny-<command>` permissions, and commands that no
+/// capability allows are rejected. See `capabilities/` for who gets what.
+const COMMANDS: &[&str] = &[
+    "get_settings",
+    "save_websites",
+    "switch_tab",
+    "open_settings",
+    "cycle_tab",
+    "previous_tab",
+    "toggle_auto_paste",
+    "save_auto_paste_selector",
+    "go_back",
+    "go_forward",
+    "save_default_website",
+    "toggle_notes",
+    "set_notes_mode",
+    "set_notes_dock",
+    "set_notes_sidebar_width",
+    "save_notes",
+    "get_notes",
+    "search_notes",
+    "export_notes",
+    "clip_selection",
+    "clip_to_notes",
+    "auto_pasted",
+    "save_global_shortcuts",
+    "get_keymap",
+    "save_keymap",
+    "show_tab_switcher",
+    "hide_tab_switcher",
+    "get_switcher_tabs",
+    "show_command_palette",
+    "hide_command_palette",
+    "search_palette",
+    "run_palette_action",
+    "show_clipboard_history",
+    "hide_clipboard_history",
+    "get_clipboard_history",
+    "paste_clipboard_entry",
+    "pin_clipboard_entry",
+    "delete_clipboard_entry",
+    "clear_clipboard_history",
+    "save_clipboard_history_settings",
+    "save_snippets",
+    "insert_snippet",
+    "send_selection",
+    "save_send_template",
+    "get_blocker_status",
+    "save_filter_lists",
+    "clear_profile_data",
+    "get_profile_data_usage",
+    "unlock",
+    "set_lock_passphrase",
+    "save_lock_idle_minutes",
+];
+
 fn main() {
-    tauri_build::build()
+    tauri_build::try_build(
+        tauri_build::Attributes::new()
+            .app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)),
+    )
+    .expect("failed to run tauri-build");
 }
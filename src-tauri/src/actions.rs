@@ -0,0 +1,197 @@
+//! Registry of app commands.
+//!
+//! Every command the app menu can trigger is listed once in `COMMANDS` with
+//! its id, label and default accelerator. The menu, the command palette, the
+//! keymap and `peko run` all look commands up here, so adding an entry makes
+//! it available everywhere. The ids are also the menu item ids.
+
+use tauri::AppHandle;
+
+use crate::clip::ClipFormat;
+use crate::dock::DockPosition;
+use crate::export::ExportFormat;
+use crate::NotesMode;
+
+pub struct Command {
+    pub id: &'static str,
+    pub label: &'static str,
+    /// Default accelerator; `AppSettings::keymap` can change it
+    pub accelerator: Option<&'static str>,
+    run: fn(&AppHandle) -> Result<(), String>,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        id: "settings",
+        label: "Settings",
+        accelerator: Some("CmdOrCtrl+Comma"),
+        run: |app| crate::open_settings(app.clone()),
+    },
+    Command {
+        id: "show_command_palette",
+        label: "Show Command Palette",
+        accelerator: Some("CmdOrCtrl+Shift+P"),
+        run: |app| crate::show_command_palette(app.clone()),
+    },
+    Command {
+        id: "show_tab_switcher",
+        label: "Show Tab Switcher",
+        accelerator: Some("CmdOrCtrl+."),
+        run: |app| crate::show_tab_switcher(app.clone()),
+    },
+    Command {
+        id: "cycle_tab",
+        label: "Next Tab",
+        accelerator: Some("CmdOrCtrl+Tab"),
+        run: |app| crate::cycle_tab(app.clone()),
+    },
+    Command {
+        id: "previous_tab",
+        label: "Previous Tab",
+        accelerator: Some("CmdOrCtrl+`"),
+        run: |app| crate::previous_tab(app.clone()),
+    },
+    Command {
+        id: "go_back",
+        label: "Back",
+        accelerator: Some("CmdOrCtrl+["),
+        run: |app| crate::go_back(app.clone()),
+    },
+    Command {
+        id: "go_forward",
+        label: "Forward",
+        accelerator: Some("CmdOrCtrl+]"),
+        run: |app| crate::go_forward(app.clone()),
+    },
+    Command {
+        id: "open_private_window",
+        label: "Open Private Window",
+        accelerator: Some("CmdOrCtrl+Shift+N"),
+        run: |app| crate::open_private_window(app.clone()),
+    },
+    Command {
+        id: "lock_app",
+        label: "Lock Peko",
+        accelerator: Some("CmdOrCtrl+Shift+L"),
+        run: |app| crate::lock_app(app),
+    },
+    Command {
+        id: "auto_paste",
+        label: "Toggle Auto-Paste on Focus",
+        accelerator: Some("CmdOrCtrl+Shift+V"),
+        run: |app| crate::toggle_auto_paste(app.clone()).map(|_| ()),
+    },
+    Command {
+        id: "show_clipboard_history",
+        label: "Show Clipboard History",
+        accelerator: Some("CmdOrCtrl+Shift+H"),
+        run: |app| crate::show_clipboard_history(app.clone()),
+    },
+    Command {
+        id: "clear_clipboard_history",
+        label: "Clear Clipboard History",
+        accelerator: None,
+        run: |app| crate::clear_clipboard_history(app.clone()),
+    },
+    Command {
+        id: "toggle_notes",
+        label: "Toggle Notes",
+        accelerator: Some("CmdOrCtrl+N"),
+        run: |app| crate::toggle_notes(app.clone()).map(|_| ()),
+    },
+    Command {
+        id: "clip_to_notes",
+        label: "Clip to Notes",
+        accelerator: Some("CmdOrCtrl+Shift+C"),
+        run: |app| crate::clip_selection(app.clone(), ClipFormat::Markdown),
+    },
+    Command {
+        id: "clip_to_notes_text",
+        label: "Clip to Notes as Plain Text",
+        accelerator: Some("CmdOrCtrl+Alt+Shift+C"),
+        run: |app| crate::clip_selection(app.clone(), ClipFormat::Text),
+    },
+    Command {
+        id: "notes_mode_hidden",
+        label: "Notes: Hide",
+        accelerator: None,
+        run: |app| crate::set_notes_mode(app.clone(), NotesMode::Hidden).map(|_| ()),
+    },
+    Command {
+        id: "notes_mode_sidebar",
+        label: "Notes: Show as Sidebar",
+        accelerator: None,
+        run: |app| crate::set_notes_mode(app.clone(), NotesMode::Sidebar).map(|_| ()),
+    },
+    Command {
+        id: "notes_mode_window",
+        label: "Notes: Show in Window",
+        accelerator: None,
+        run: |app| crate::set_notes_mode(app.clone(), NotesMode::Window).map(|_| ()),
+    },
+    Command {
+        id: "notes_dock_left",
+        label: "Notes: Dock Left",
+        accelerator: None,
+        run: |app| crate::set_notes_dock(app.clone(), DockPosition::Left),
+    },
+    Command {
+        id: "notes_dock_right",
+        label: "Notes: Dock Right",
+        accelerator: None,
+        run: |app| crate::set_notes_dock(app.clone(), DockPosition::Right),
+    },
+    Command {
+        id: "notes_dock_bottom",
+        label: "Notes: Dock Bottom",
+        accelerator: None,
+        run: |app| crate::set_notes_dock(app.clone(), DockPosition::Bottom),
+    },
+    Command {
+        id: "export_notes_html",
+        label: "Notes: Export as HTML",
+        accelerator: None,
+        run: |app| crate::start_notes_export(app, ExportFormat::Html),
+    },
+    Command {
+        id: "export_notes_pdf",
+        label: "Notes: Export as PDF",
+        accelerator: None,
+        run: |app| crate::start_notes_export(app, ExportFormat::Pdf),
+    },
+    Command {
+        id: "export_notes_markdown",
+        label: "Notes: Export as Markdown Bundle",
+        accelerator: None,
+        run: |app| crate::start_notes_export(app, ExportFormat::Markdown),
+    },
+];
+
+pub fn find(id: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.id == id)
+}
+
+/// Run the command with the given id.
+pub fn run(app: &AppHandle, id: &str) -> Result<(), String> {
+    let command = find(id).ok_or_else(|| format!("Unknown action: {}", id))?;
+    (command.run)(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_ids_are_unique() {
+        for (i, command) in COMMANDS.iter().enumerate() {
+            assert!(
+                COMMANDS[i + 1..].iter().all(|other| other.id != command.id),
+                "duplicate command id {}",
+                command.id
+            );
+            assert!(!command.id.starts_with("tab_"), "{} clashes with the tab bindings", command.id);
+        }
+        assert!(find("toggle_notes").is_some());
+        assert!(find("launch_missiles").is_none());
+    }
+}
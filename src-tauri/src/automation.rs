@@ -0,0 +1,452 @@
+//! Local automation API for editor plugins and scripts.
+//!
+//! JSON-RPC 2.0 over a Unix socket (`peko-automation.sock` in the app data
+//! directory), one message per line. The socket is only accessible to the
+//! current user, and every connection must first call `authenticate` with
+//! the token stored in `automation_token` next to it.
+//!
+//! Methods:
+//!
+//! | Method | Params | Result |
+//! |--------|--------|--------|
+//! | `authenticate` | `{token}` | `true` |
+//! | `list_sites` | — | `{websites, active_tab}` |
+//! | `switch_tab` | `{tab_id}` | `null` |
+//! | `open_url` | `{url, site?}` | `null` |
+//! | `get_current_page` | `{site?}` | `{site, url, title}` |
+//! | `get_notes` | — | notes text |
+//! | `append_notes` | `{text}` | `null` |
+//!
+//! There is deliberately no way to run scripts in a page.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SOCKET_NAME: &str = "peko-automation.sock";
+const TOKEN_NAME: &str = "automation_token";
+
+/// Time a new connection gets to authenticate.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// The request failed in the app, e.g. an unknown site.
+pub const APP_ERROR: i64 = -32000;
+pub const UNAUTHORIZED: i64 = -32001;
+
+/// A call the API accepts. Params are deserialized into the variant fields.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "method", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Method {
+    ListSites,
+    SwitchTab { tab_id: String },
+    OpenUrl { url: String, site: Option<String> },
+    GetCurrentPage { site: Option<String> },
+    GetNotes,
+    AppendNotes { text: String },
+}
+
+const METHODS: &[&str] = &[
+    "list_sites",
+    "switch_tab",
+    "open_url",
+    "get_current_page",
+    "get_notes",
+    "append_notes",
+];
+
+/// Runs API calls against the app. Implemented by the running instance and
+/// by test doubles.
+pub trait Backend: Send + Sync + 'static {
+    fn call(&self, method: Method) -> Result<Value, String>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        RpcResponse { jsonrpc: "2.0".to_string(), id, result, error }
+    }
+}
+
+pub fn socket_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SOCKET_NAME)
+}
+
+pub fn token_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(TOKEN_NAME)
+}
+
+/// Read the token, creating it (readable only by the current user) on first
+/// use. It stays the same across restarts so scripts can keep using it.
+pub fn load_or_create_token(data_dir: &Path) -> Result<String, String> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let path = token_path(data_dir);
+    match std::fs::read_to_string(&path) {
+        Ok(token) if !token.trim().is_empty() => return Ok(token.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read automation token: {}", e)),
+    }
+
+    let token = crate::random_token()?;
+    std::fs::create_dir_all(data_dir)
+        .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .map_err(|e| format!("Failed to write automation token: {}", e))?;
+    file.write_all(token.as_bytes())
+        .map_err(|e| format!("Failed to write automation token: {}", e))?;
+    Ok(token)
+}
+
+/// Start the API for the running app.
+pub fn start_server(app: &tauri::AppHandle) -> Result<(), String> {
+    use tauri::Manager;
+
+    let data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let token = load_or_create_token(&data_dir)?;
+    let path = socket_path(&data_dir);
+    let listener = crate::control::bind_private_socket(&path)?;
+
+    serve(listener, token, Arc::new(AppBackend(app.clone())));
+    log::info!("Automation API listening at {}", path.display());
+    Ok(())
+}
+
+/// Accept connections on a background thread, one thread per connection so
+/// a long-lived editor connection doesn't block scripts.
+pub fn serve(listener: UnixListener, token: String, backend: Arc<dyn Backend>) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let token = token.clone();
+                    let backend = backend.clone();
+                    std::thread::spawn(move || handle_connection(stream, &token, backend.as_ref()));
+                }
+                Err(e) => log::warn!("Automation socket accept failed: {}", e),
+            }
+        }
+    });
+}
+
+fn handle_connection(stream: UnixStream, token: &str, backend: &dyn Backend) {
+    let _ = stream.set_read_timeout(Some(AUTH_TIMEOUT));
+    let mut reader = BufReader::new(&stream);
+    let mut writer = &stream;
+    let mut authenticated = false;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let was_authenticated = authenticated;
+        if let Some(response) = handle_message(&line, token, &mut authenticated, backend) {
+            let mut out = serde_json::to_string(&response).unwrap_or_default();
+            out.push('\n');
+            if writer.write_all(out.as_bytes()).is_err() {
+                break;
+            }
+        }
+
+        // Only one attempt to authenticate per connection
+        if !authenticated {
+            break;
+        }
+        if !was_authenticated {
+            let _ = stream.set_read_timeout(None);
+        }
+    }
+}
+
+/// Handle one line. Returns `None` for notifications (requests without an
+/// id), which get no response.
+fn handle_message(line: &str, token: &str, authenticated: &mut bool, backend: &dyn Backend) -> Option<RpcResponse> {
+    let request: RpcRequest = match serde_json::from_str::<Value>(line) {
+        Err(e) => return Some(RpcResponse::new(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string())))),
+        Ok(value) => match serde_json::from_value(value) {
+            Ok(request) => request,
+            Err(e) => return Some(RpcResponse::new(Value::Null, Err(RpcError::new(INVALID_REQUEST, e.to_string())))),
+        },
+    };
+    let id = request.id.clone();
+    let result = handle_request(request, token, authenticated, backend);
+    id.map(|id| RpcResponse::new(id, result))
+}
+
+fn handle_request(request: RpcRequest, token: &str, authenticated: &mut bool, backend: &dyn Backend) -> Result<Value, RpcError> {
+    if request.jsonrpc != "2.0" {
+        return Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""));
+    }
+
+    if request.method == "authenticate" {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Params {
+            token: String,
+        }
+        let params: Params = parse_params(request.params)?;
+        if !constant_time_eq(params.token.as_bytes(), token.as_bytes()) {
+            return Err(RpcError::new(UNAUTHORIZED, "Invalid token"));
+        }
+        *authenticated = true;
+        return Ok(Value::Bool(true));
+    }
+
+    if !*authenticated {
+        return Err(RpcError::new(UNAUTHORIZED, "Call authenticate first"));
+    }
+
+    let method = parse_method(&request.method, request.params)?;
+    log::info!("Automation call: {:?}", method);
+    backend.call(method).map_err(|e| RpcError::new(APP_ERROR, e))
+}
+
+fn parse_method(name: &str, params: Value) -> Result<Method, RpcError> {
+    if !METHODS.contains(&name) {
+        return Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", name)));
+    }
+    let mut params = match params {
+        Value::Null => serde_json::Map::new(),
+        Value::Object(params) => params,
+        _ => return Err(RpcError::new(INVALID_PARAMS, "params must be an object")),
+    };
+    params.insert("method".to_string(), Value::String(name.to_string()));
+    parse_params(Value::Object(params))
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// The running app. Calls are run on the main thread through the same
+/// functions the IPC commands use.
+struct AppBackend(tauri::AppHandle);
+
+impl Backend for AppBackend {
+    fn call(&self, method: Method) -> Result<Value, String> {
+        crate::control::run_on_main_thread(&self.0, move |app| call_app(app, method))
+    }
+}
+
+fn call_app(app: &tauri::AppHandle, method: Method) -> Result<Value, String> {
+    use tauri::Manager;
+
+    if crate::locked_out(app) {
+        return Err("Peko is locked".to_string());
+    }
+    match method {
+        Method::ListSites => {
+            let settings = app.state::<crate::SettingsState>().0.lock().unwrap().clone();
+            serde_json::to_value(crate::control::SiteList {
+                websites: settings.websites,
+                active_tab: settings.active_tab,
+            })
+            .map_err(|e| e.to_string())
+        }
+        Method::SwitchTab { tab_id } => {
+            crate::open_in_site(app, Some(tab_id), None)?;
+            Ok(Value::Null)
+        }
+        Method::OpenUrl { url, site } => {
+            crate::open_in_site(app, site, Some(url))?;
+            Ok(Value::Null)
+        }
+        Method::GetCurrentPage { site } => {
+            let site = match site {
+                Some(site) => site,
+                None => app.state::<crate::SettingsState>().0.lock().unwrap().active_tab.clone(),
+            };
+            let window = app.get_webview_window(&site)
+                .filter(|_| !crate::RESERVED_WINDOW_LABELS.contains(&site.as_str()))
+                .ok_or_else(|| format!("Unknown site: {}", site))?;
+            let url = window.url().map_err(|e| e.to_string())?;
+            let title = crate::page_title(app, &site).unwrap_or_default();
+            Ok(serde_json::json!({ "site": site, "url": url.as_str(), "title": title }))
+        }
+        Method::GetNotes => crate::get_notes(app.clone()).map(Value::String),
+        Method::AppendNotes { text } => {
+            crate::append_to_notes(app, &format!("{}\n", text.trim_end()))?;
+            Ok(Value::Null)
+        }
+    }
+}
+
+/// Client for the API, used by the integration tests and usable from Rust
+/// tooling.
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connect and authenticate.
+    pub fn connect(socket: &Path, token: &str) -> Result<Client, RpcError> {
+        let io_error = |e: io::Error| RpcError::new(INTERNAL_ERROR, e.to_string());
+        let writer = UnixStream::connect(socket).map_err(io_error)?;
+        let reader = BufReader::new(writer.try_clone().map_err(io_error)?);
+        let mut client = Client { reader, writer, next_id: 1 };
+        client.call("authenticate", serde_json::json!({ "token": token }))?;
+        Ok(client)
+    }
+
+    /// Connect to the running app using the token in its data directory.
+    pub fn connect_to_app(data_dir: &Path) -> Result<Client, RpcError> {
+        let token = std::fs::read_to_string(token_path(data_dir))
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, format!("Failed to read automation token: {}", e)))?;
+        Client::connect(&socket_path(data_dir), token.trim())
+    }
+
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let io_error = |e: io::Error| RpcError::new(INTERNAL_ERROR, e.to_string());
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let mut line = request.to_string();
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).map_err(io_error)?;
+
+        let mut response = String::new();
+        if self.reader.read_line(&mut response).map_err(io_error)? == 0 {
+            return Err(RpcError::new(INTERNAL_ERROR, "Connection closed"));
+        }
+        let response: RpcResponse = serde_json::from_str(&response)
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, format!("Invalid response: {}", e)))?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error),
+            (Some(result), None) => Ok(result),
+            (None, None) => Ok(Value::Null),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl Backend for Echo {
+        fn call(&self, method: Method) -> Result<Value, String> {
+            Ok(Value::String(format!("{:?}", method)))
+        }
+    }
+
+    fn send(line: &str, authenticated: &mut bool) -> Option<RpcResponse> {
+        handle_message(line, "secret", authenticated, &Echo)
+    }
+
+    #[test]
+    fn test_requires_authentication() {
+        let mut authenticated = false;
+        let call = r#"{"jsonrpc":"2.0","id":1,"method":"get_notes"}"#;
+        assert_eq!(send(call, &mut authenticated).unwrap().error.unwrap().code, UNAUTHORIZED);
+
+        let bad = r#"{"jsonrpc":"2.0","id":2,"method":"authenticate","params":{"token":"guess"}}"#;
+        assert_eq!(send(bad, &mut authenticated).unwrap().error.unwrap().code, UNAUTHORIZED);
+        assert!(!authenticated);
+
+        let good = r#"{"jsonrpc":"2.0","id":3,"method":"authenticate","params":{"token":"secret"}}"#;
+        assert_eq!(send(good, &mut authenticated).unwrap().result, Some(Value::Bool(true)));
+        assert!(authenticated);
+        assert!(send(call, &mut authenticated).unwrap().error.is_none());
+    }
+
+    #[test]
+    fn test_parse_method() {
+        assert_eq!(parse_method("list_sites", Value::Null), Ok(Method::ListSites));
+        assert_eq!(
+            parse_method("open_url", serde_json::json!({ "url": "https://example.com" })),
+            Ok(Method::OpenUrl { url: "https://example.com".to_string(), site: None })
+        );
+        assert_eq!(parse_method("eval", Value::Null).unwrap_err().code, METHOD_NOT_FOUND);
+        assert_eq!(parse_method("switch_tab", Value::Null).unwrap_err().code, INVALID_PARAMS);
+        assert_eq!(
+            parse_method("switch_tab", serde_json::json!({ "tab_id": "gemini", "script": "x" })).unwrap_err().code,
+            INVALID_PARAMS
+        );
+        assert_eq!(parse_method("switch_tab", serde_json::json!(["gemini"])).unwrap_err().code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_malformed_messages() {
+        let mut authenticated = true;
+        assert_eq!(send("{not json", &mut authenticated).unwrap().error.unwrap().code, PARSE_ERROR);
+        assert_eq!(send(r#"{"id":1}"#, &mut authenticated).unwrap().error.unwrap().code, INVALID_REQUEST);
+        assert_eq!(
+            send(r#"{"jsonrpc":"1.0","id":1,"method":"get_notes"}"#, &mut authenticated).unwrap().error.unwrap().code,
+            INVALID_REQUEST
+        );
+        // Notifications get no response
+        assert!(send(r#"{"jsonrpc":"2.0","method":"get_notes"}"#, &mut authenticated).is_none());
+    }
+}
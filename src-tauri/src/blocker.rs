@@ -0,0 +1,1426 @@
+//! Content blocking with EasyList/uBlock-style filter lists read from local
+//! files.
+//!
+//! Network filters (`||ads.example^$third-party`, `@@` exceptions) are
+//! checked against navigations of site windows and against the requests
+//! their pages make (scripts, images, XHR, frames …). WebView2 hands each
+//! request to Peko; WebKit on Linux and macOS can't, so there the filters
+//! are compiled to a content rule list that WebKit applies itself. Filters
+//! the rule syntax can't express only apply to navigations there.
+//! Cosmetic filters (`##.ad`, `example.com##.banner`, `#@#` exceptions)
+//! become a stylesheet that hides the matching elements.
+//!
+//! Regex filters, procedural and scriptlet filters and options that change
+//! rather than block requests (`redirect=`, `csp=` …) are skipped and
+//! counted as such.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use url::Url;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceType {
+    Script,
+    Image,
+    Stylesheet,
+    Object,
+    Xhr,
+    Subdocument,
+    Ping,
+    Media,
+    Font,
+    Websocket,
+    Other,
+    Popup,
+    Document,
+}
+
+impl ResourceType {
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    fn from_option(name: &str) -> Option<Self> {
+        Some(match name {
+            "script" => ResourceType::Script,
+            "image" => ResourceType::Image,
+            "stylesheet" | "css" => ResourceType::Stylesheet,
+            "object" => ResourceType::Object,
+            "xmlhttprequest" | "xhr" => ResourceType::Xhr,
+            "subdocument" | "frame" => ResourceType::Subdocument,
+            "ping" | "beacon" => ResourceType::Ping,
+            "media" => ResourceType::Media,
+            "font" => ResourceType::Font,
+            "websocket" => ResourceType::Websocket,
+            "other" => ResourceType::Other,
+            "popup" => ResourceType::Popup,
+            "document" | "doc" => ResourceType::Document,
+            _ => return None,
+        })
+    }
+
+    /// The `resource-type` of WebKit content rules covering this type. Frames
+    /// are documents to WebKit, and what it has no type for is `raw`.
+    #[cfg(any(target_os = "linux", target_os = "macos", test))]
+    fn webkit_type(self) -> &'static str {
+        match self {
+            ResourceType::Script => "script",
+            ResourceType::Image => "image",
+            ResourceType::Stylesheet => "style-sheet",
+            ResourceType::Font => "font",
+            ResourceType::Media => "media",
+            ResourceType::Popup => "popup",
+            ResourceType::Subdocument | ResourceType::Document => "document",
+            ResourceType::Object | ResourceType::Xhr | ResourceType::Ping | ResourceType::Websocket | ResourceType::Other => {
+                "raw"
+            }
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+const RESOURCE_TYPES: [ResourceType; 13] = [
+    ResourceType::Script,
+    ResourceType::Image,
+    ResourceType::Stylesheet,
+    ResourceType::Object,
+    ResourceType::Xhr,
+    ResourceType::Subdocument,
+    ResourceType::Ping,
+    ResourceType::Media,
+    ResourceType::Font,
+    ResourceType::Websocket,
+    ResourceType::Other,
+    ResourceType::Popup,
+    ResourceType::Document,
+];
+
+/// Types a filter without type options applies to. Popups and documents
+/// must be asked for explicitly.
+const DEFAULT_TYPES: u16 = !(1 << ResourceType::Popup as u16 | 1 << ResourceType::Document as u16);
+
+/// Options that don't affect whether a request is blocked.
+const IGNORED_OPTIONS: &[&str] = &["all", "~all", "genericblock", "elemhide", "generichide"];
+
+/// Pseudo-classes of procedural filters, which plain CSS can't express.
+const PROCEDURAL: &[&str] = &[
+    ":-abp-", ":has-text(", ":contains(", ":xpath(", ":style(", ":matches-css", ":upward(", ":remove(",
+    ":min-text-length(", ":watch-attr(", ":others(", ":matches-path(", ":matches-attr(", ":if(", ":if-not(",
+];
+
+/// Words in URLs too common to pick a filter out by.
+const COMMON_WORDS: &[&str] = &["http", "https", "www", "com", "net", "org", "js", "html", "php"];
+
+/// A request to check against the network filters.
+pub struct Request<'a> {
+    url: &'a Url,
+    /// Lowercased, as filters are case-insensitive by default
+    lower: String,
+    /// Host of the page making the request
+    source_host: &'a str,
+    resource_type: ResourceType,
+}
+
+impl<'a> Request<'a> {
+    pub fn new(url: &'a Url, source_host: &'a str, resource_type: ResourceType) -> Self {
+        Request { url, lower: url.as_str().to_lowercase(), source_host, resource_type }
+    }
+
+    fn host(&self) -> &str {
+        self.url.host_str().unwrap_or_default()
+    }
+
+    fn third_party(&self) -> bool {
+        base_domain(self.host()) != base_domain(self.source_host)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    /// `^`: a character that can't be part of a host or path word, or the end
+    Separator,
+}
+
+#[derive(Debug, PartialEq)]
+enum Anchor {
+    None,
+    /// `|`: the start of the URL
+    Start,
+    /// `||`: the start of the host or of one of its subdomains
+    Host,
+}
+
+#[derive(Debug)]
+struct NetworkFilter {
+    /// The pattern split at its `*`s. Without an anchor the first segment
+    /// is empty, as if the pattern started with `*`.
+    segments: Vec<Vec<Part>>,
+    anchor: Anchor,
+    /// Trailing `|`: the end of the URL
+    end_anchor: bool,
+    match_case: bool,
+    important: bool,
+    third_party: Option<bool>,
+    /// Page domains from `domain=`, with whether each is included
+    domains: Vec<(String, bool)>,
+    types: u16,
+}
+
+impl NetworkFilter {
+    fn matches(&self, request: &Request) -> bool {
+        if self.types & request.resource_type.bit() == 0 {
+            return false;
+        }
+        if self.third_party.is_some_and(|third_party| third_party != request.third_party()) {
+            return false;
+        }
+        if !domains_match(&self.domains, request.source_host) {
+            return false;
+        }
+
+        let text = if self.match_case { request.url.as_str() } else { request.lower.as_str() };
+        let text = text.as_bytes();
+        match self.anchor {
+            Anchor::Start | Anchor::None => match_segments(&self.segments, text, 0, self.end_anchor),
+            Anchor::Host => {
+                let start = request.url[..url::Position::BeforeHost].len();
+                let end = request.url[..url::Position::AfterHost].len();
+                (start..end)
+                    .filter(|&i| i == start || text[i - 1] == b'.')
+                    .any(|i| match_segments(&self.segments, text, i, self.end_anchor))
+            }
+        }
+    }
+
+    /// Words of the pattern that every URL it matches has as whole words,
+    /// lowercased: the ones bounded by a separator, an anchor or a
+    /// character that isn't part of a word.
+    fn words(&self) -> Vec<String> {
+        let mut words = Vec::new();
+        let last = self.segments.len() - 1;
+        for (s, segment) in self.segments.iter().enumerate() {
+            for (p, part) in segment.iter().enumerate() {
+                let Part::Literal(literal) = part else {
+                    continue;
+                };
+                // Literals are separated by separators within a segment
+                let bounded_start = p > 0 || (s == 0 && self.anchor != Anchor::None);
+                let bounded_end = p + 1 < segment.len() || (s == last && self.end_anchor);
+                let bytes = literal.as_bytes();
+                let mut i = 0;
+                while i < bytes.len() {
+                    if !is_word(bytes[i]) {
+                        i += 1;
+                        continue;
+                    }
+                    let start = i;
+                    while i < bytes.len() && is_word(bytes[i]) {
+                        i += 1;
+                    }
+                    if (start > 0 || bounded_start) && (i < bytes.len() || bounded_end) {
+                        words.push(literal[start..i].to_lowercase());
+                    }
+                }
+            }
+        }
+        words
+    }
+
+    /// The filter as the trigger of a WebKit content rule, or `None` if the
+    /// rule syntax can't express it.
+    #[cfg(any(target_os = "linux", target_os = "macos", test))]
+    fn content_trigger(&self) -> Option<serde_json::Value> {
+        const NOT_SEPARATOR: &str = "a-zA-Z0-9_.%-";
+        let mut regex = String::new();
+        match self.anchor {
+            Anchor::Host => regex.push_str(r"^[a-z][a-z0-9.+-]*://([^/]*\.)?"),
+            Anchor::Start => regex.push('^'),
+            Anchor::None => {}
+        }
+        // WebKit looks for unanchored patterns anywhere already
+        let segments = if self.anchor == Anchor::None { &self.segments[1..] } else { &self.segments[..] };
+        let mut ended = false;
+        for (s, segment) in segments.iter().enumerate() {
+            if s > 0 {
+                regex.push_str(".*");
+            }
+            for (p, part) in segment.iter().enumerate() {
+                match part {
+                    Part::Literal(literal) => {
+                        if !literal.is_ascii() {
+                            return None;
+                        }
+                        for c in literal.chars() {
+                            if r"\.*+?^$|()[]{}".contains(c) {
+                                regex.push('\\');
+                            }
+                            regex.push(c);
+                        }
+                    }
+                    // Only a separator ending the pattern can match the end
+                    // of the URL, which takes an optional group
+                    Part::Separator if s + 1 == segments.len() && p + 1 == segment.len() => {
+                        let rest = if self.end_anchor { "" } else { ".*" };
+                        regex.push_str(&format!("([^{}]{})?$", NOT_SEPARATOR, rest));
+                        ended = true;
+                    }
+                    Part::Separator => regex.push_str(&format!("[^{}]", NOT_SEPARATOR)),
+                }
+            }
+        }
+        if self.end_anchor && !ended {
+            regex.push('$');
+        }
+        if regex.is_empty() {
+            regex.push_str(".*");
+        }
+
+        let mut types: Vec<&str> = RESOURCE_TYPES
+            .iter()
+            .filter(|t| self.types & t.bit() != 0)
+            .map(|t| t.webkit_type())
+            .collect();
+        types.sort_unstable();
+        types.dedup();
+        if types.is_empty() {
+            return None;
+        }
+        let mut trigger = serde_json::json!({ "url-filter": regex, "resource-type": types });
+        if self.match_case {
+            trigger["url-filter-is-case-sensitive"] = true.into();
+        }
+        if let Some(third_party) = self.third_party {
+            trigger["load-type"] = serde_json::json!([if third_party { "third-party" } else { "first-party" }]);
+        }
+        // A trigger takes either included or excluded page domains
+        if self.domains.iter().any(|(domain, _)| !domain.is_ascii()) {
+            return None;
+        }
+        let domains = |include: bool| -> Vec<String> {
+            self.domains.iter().filter(|d| d.1 == include).map(|(domain, _)| format!("*{}", domain)).collect()
+        };
+        match (domains(true), domains(false)) {
+            (included, excluded) if included.is_empty() && excluded.is_empty() => {}
+            (included, excluded) if excluded.is_empty() => trigger["if-domain"] = included.into(),
+            (included, excluded) if included.is_empty() => trigger["unless-domain"] = excluded.into(),
+            _ => return None,
+        }
+        Some(trigger)
+    }
+}
+
+fn is_separator(c: u8) -> bool {
+    !(c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.' | b'%'))
+}
+
+/// Characters of the words filters are indexed by.
+fn is_word(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'%'
+}
+
+fn url_words(url: &str) -> HashSet<&str> {
+    url.split(|c: char| !c.is_ascii() || !is_word(c as u8)).filter(|word| !word.is_empty()).collect()
+}
+
+/// Where `segment` ends if it matches `text` at `pos`.
+fn match_segment(segment: &[Part], text: &[u8], mut pos: usize) -> Option<usize> {
+    for part in segment {
+        match part {
+            Part::Literal(literal) => {
+                if !text[pos..].starts_with(literal.as_bytes()) {
+                    return None;
+                }
+                pos += literal.len();
+            }
+            Part::Separator if pos == text.len() => {}
+            Part::Separator if is_separator(text[pos]) => pos += 1,
+            Part::Separator => return None,
+        }
+    }
+    Some(pos)
+}
+
+/// Whether the segments match `text` from `pos`: the first one right there,
+/// each later one at the first place after the one before. A segment placed
+/// earlier never ends later, so the first place can't lose a match and
+/// nothing is retried. The last segment may have to end the URL, so it is
+/// the one tried at every place left.
+fn match_segments(segments: &[Vec<Part>], text: &[u8], pos: usize, end_anchor: bool) -> bool {
+    let Some(mut pos) = match_segment(&segments[0], text, pos) else {
+        return false;
+    };
+    let Some((last, middle)) = segments[1..].split_last() else {
+        return !end_anchor || pos == text.len();
+    };
+    for segment in middle {
+        match (pos..=text.len()).find_map(|i| match_segment(segment, text, i)) {
+            Some(end) => pos = end,
+            None => return false,
+        }
+    }
+    (pos..=text.len())
+        .filter_map(|i| match_segment(last, text, i))
+        .any(|end| !end_anchor || end == text.len())
+}
+
+/// Whether `host` is allowed by a domain list: it must match an included
+/// domain, if there are any, and no excluded one.
+fn domains_match(domains: &[(String, bool)], host: &str) -> bool {
+    let on = |domain: &str| host == domain || host.strip_suffix(domain).is_some_and(|rest| rest.ends_with('.'));
+    let mut included = None;
+    for (domain, include) in domains {
+        if !include && on(domain) {
+            return false;
+        }
+        if *include {
+            included = Some(included.unwrap_or(false) || on(domain));
+        }
+    }
+    included.unwrap_or(true)
+}
+
+/// The registrable part of a host, approximated without the public suffix
+/// list: the last two labels, or three for suffixes like `co.uk`.
+fn base_domain(host: &str) -> &str {
+    let labels: Vec<&str> = host.rsplitn(4, '.').collect();
+    let keep = match labels.as_slice() {
+        [tld, second, _, ..] if tld.len() == 2 && second.len() <= 3 => 3,
+        _ => 2,
+    };
+    if labels.len() <= keep {
+        return host;
+    }
+    let cut: usize = labels[..keep].iter().map(|label| label.len() + 1).sum();
+    &host[host.len() + 1 - cut..]
+}
+
+#[derive(Debug)]
+struct CosmeticFilter {
+    domains: Vec<(String, bool)>,
+    selector: String,
+}
+
+impl CosmeticFilter {
+    fn applies_to(&self, host: &str) -> bool {
+        self.domains.is_empty() || domains_match(&self.domains, host)
+    }
+}
+
+enum Parsed {
+    Block(NetworkFilter),
+    Allow(NetworkFilter),
+    Hide(CosmeticFilter),
+    Unhide(CosmeticFilter),
+    Comment,
+    Skipped,
+}
+
+fn parse_line(line: &str) -> Parsed {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('!') || line.starts_with('[') {
+        return Parsed::Comment;
+    }
+    if let Some(i) = line.find('#') {
+        let rest = &line[i..];
+        if rest.starts_with("##") || rest.starts_with("#@#") {
+            return parse_cosmetic(&line[..i], rest);
+        }
+        // #?#, #$#, #%#, #@?# … are procedural, CSS injection or scriptlets
+        if rest.len() > 2 && rest[1..].find('#').is_some_and(|j| j <= 3) {
+            return Parsed::Skipped;
+        }
+    }
+    match parse_network(line) {
+        Some((filter, true)) => Parsed::Allow(filter),
+        Some((filter, false)) => Parsed::Block(filter),
+        None => Parsed::Skipped,
+    }
+}
+
+fn parse_cosmetic(domains: &str, rest: &str) -> Parsed {
+    let (exception, selector) = match rest.strip_prefix("#@#") {
+        Some(selector) => (true, selector.trim()),
+        None => (false, rest[2..].trim()),
+    };
+    if selector.is_empty()
+        || selector.starts_with('+')
+        || selector.starts_with('^')
+        || selector.contains(['{', '}'])
+        || PROCEDURAL.iter().any(|p| selector.contains(p))
+    {
+        return Parsed::Skipped;
+    }
+    let Some(domains) = parse_domains(domains, ',') else {
+        return Parsed::Skipped;
+    };
+    let filter = CosmeticFilter { domains, selector: selector.to_string() };
+    if exception {
+        Parsed::Unhide(filter)
+    } else {
+        Parsed::Hide(filter)
+    }
+}
+
+/// `a.com,~b.com` (or `|`-separated in `domain=`). `None` for entity
+/// domains like `example.*`, which aren't supported.
+fn parse_domains(list: &str, separator: char) -> Option<Vec<(String, bool)>> {
+    let mut domains = Vec::new();
+    for domain in list.split(separator).map(str::trim).filter(|d| !d.is_empty()) {
+        let (domain, include) = match domain.strip_prefix('~') {
+            Some(domain) => (domain, false),
+            None => (domain, true),
+        };
+        if domain.ends_with(".*") {
+            return None;
+        }
+        domains.push((domain.to_lowercase(), include));
+    }
+    Some(domains)
+}
+
+/// A network filter and whether it is an exception.
+fn parse_network(line: &str) -> Option<(NetworkFilter, bool)> {
+    let (exception, line) = match line.strip_prefix("@@") {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (pattern, options) = match line.rfind('$') {
+        Some(i) => (&line[..i], Some(&line[i + 1..])),
+        None => (line, None),
+    };
+    if pattern.len() > 2 && pattern.starts_with('/') && pattern.ends_with('/') {
+        return None;
+    }
+
+    let mut filter = NetworkFilter {
+        segments: vec![Vec::new()],
+        anchor: Anchor::None,
+        end_anchor: false,
+        match_case: false,
+        important: false,
+        third_party: None,
+        domains: Vec::new(),
+        types: DEFAULT_TYPES,
+    };
+
+    let mut include = 0u16;
+    let mut exclude = 0u16;
+    for option in options.unwrap_or_default().split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        let (name, negated) = match option.strip_prefix('~') {
+            Some(name) => (name, true),
+            None => (option, false),
+        };
+        match name {
+            "third-party" | "3p" => filter.third_party = Some(!negated),
+            "first-party" | "1p" => filter.third_party = Some(negated),
+            "match-case" => filter.match_case = true,
+            "important" => filter.important = true,
+            _ if name.starts_with("domain=") => filter.domains = parse_domains(&name["domain=".len()..], '|')?,
+            _ if IGNORED_OPTIONS.contains(&option) => {}
+            _ => {
+                let bit = ResourceType::from_option(name)?.bit();
+                if negated {
+                    exclude |= bit;
+                } else {
+                    include |= bit;
+                }
+            }
+        }
+    }
+    if include != 0 {
+        filter.types = include & !exclude;
+    } else if exclude != 0 {
+        filter.types = DEFAULT_TYPES & !exclude;
+    }
+
+    let mut pattern = pattern;
+    if let Some(rest) = pattern.strip_prefix("||") {
+        filter.anchor = Anchor::Host;
+        pattern = rest;
+    } else if let Some(rest) = pattern.strip_prefix('|') {
+        filter.anchor = Anchor::Start;
+        pattern = rest;
+    }
+    if let Some(rest) = pattern.strip_suffix('|') {
+        filter.end_anchor = true;
+        pattern = rest;
+    }
+    // A filter that matches everything only makes sense scoped by options
+    if pattern.trim_matches('*').is_empty() && filter.domains.is_empty() && filter.third_party.is_none() {
+        return None;
+    }
+
+    let pattern = if filter.match_case { pattern.to_string() } else { pattern.to_lowercase() };
+    if filter.anchor == Anchor::None {
+        filter.segments.push(Vec::new());
+    }
+    let mut literal = String::new();
+    for c in pattern.chars() {
+        if c != '*' && c != '^' {
+            literal.push(c);
+            continue;
+        }
+        let segment = filter.segments.last_mut().unwrap();
+        if !literal.is_empty() {
+            segment.push(Part::Literal(std::mem::take(&mut literal)));
+        }
+        if c == '*' {
+            filter.segments.push(Vec::new());
+        } else {
+            segment.push(Part::Separator);
+        }
+    }
+    if !literal.is_empty() {
+        filter.segments.last_mut().unwrap().push(Part::Literal(literal));
+    }
+    Some((filter, exception))
+}
+
+/// Network filters by a word every URL they match has, so that a request
+/// is only checked against the filters of its URL's words.
+#[derive(Default)]
+struct FilterIndex {
+    by_word: HashMap<String, Vec<NetworkFilter>>,
+    /// Filters without such a word, checked against every request
+    other: Vec<NetworkFilter>,
+}
+
+impl FilterIndex {
+    /// File `filter` under the word that picks it out best: an uncommon one
+    /// shared with the fewest filters so far, then the longest.
+    fn insert(&mut self, filter: NetworkFilter) {
+        let word = filter.words().into_iter().min_by_key(|word| {
+            let shared = self.by_word.get(word).map_or(0, Vec::len);
+            (COMMON_WORDS.contains(&word.as_str()), shared, std::cmp::Reverse(word.len()))
+        });
+        match word {
+            Some(word) => self.by_word.entry(word).or_default().push(filter),
+            None => self.other.push(filter),
+        }
+    }
+
+    /// Filters that may match a URL with `words`.
+    fn candidates<'a>(&'a self, words: &'a HashSet<&str>) -> impl Iterator<Item = &'a NetworkFilter> {
+        words.iter().filter_map(|word| self.by_word.get(*word)).flatten().chain(&self.other)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", test))]
+    fn iter(&self) -> impl Iterator<Item = &NetworkFilter> {
+        self.by_word.values().flatten().chain(&self.other)
+    }
+}
+
+/// Filters from every configured list.
+#[derive(Default)]
+pub struct FilterSet {
+    block: FilterIndex,
+    allow: FilterIndex,
+    hide: Vec<CosmeticFilter>,
+    unhide: Vec<CosmeticFilter>,
+    /// The network filters as a WebKit content rule list, made when first
+    /// asked for
+    #[cfg(any(target_os = "linux", target_os = "macos", test))]
+    content_rules: std::sync::OnceLock<Option<Arc<str>>>,
+}
+
+/// What a list contributed, as shown in the settings.
+#[derive(Serialize, Clone, Debug, PartialEq, Default)]
+pub struct ListStatus {
+    pub path: String,
+    pub network_filters: usize,
+    pub cosmetic_filters: usize,
+    /// Filters Peko doesn't support
+    pub skipped: usize,
+    pub error: Option<String>,
+}
+
+impl FilterSet {
+    /// Add the filters in `text`.
+    pub fn add_list(&mut self, text: &str) -> ListStatus {
+        let mut status = ListStatus::default();
+        for line in text.lines() {
+            match parse_line(line) {
+                Parsed::Block(filter) => {
+                    self.block.insert(filter);
+                    status.network_filters += 1;
+                }
+                Parsed::Allow(filter) => {
+                    self.allow.insert(filter);
+                    status.network_filters += 1;
+                }
+                Parsed::Hide(filter) => {
+                    self.hide.push(filter);
+                    status.cosmetic_filters += 1;
+                }
+                Parsed::Unhide(filter) => {
+                    self.unhide.push(filter);
+                    status.cosmetic_filters += 1;
+                }
+                Parsed::Comment => {}
+                Parsed::Skipped => status.skipped += 1,
+            }
+        }
+        status
+    }
+
+    /// Whether a blocking filter matches and no exception overrides it.
+    /// `$important` filters win over exceptions.
+    pub fn should_block(&self, request: &Request) -> bool {
+        let words = url_words(&request.lower);
+        let mut blocked = false;
+        for filter in self.block.candidates(&words).filter(|filter| filter.matches(request)) {
+            if filter.important {
+                return true;
+            }
+            blocked = true;
+        }
+        blocked && !self.allow.candidates(&words).any(|filter| filter.matches(request))
+    }
+
+    /// The network filters as a WebKit content rule list, in the order its
+    /// rules apply: blocks, then exceptions, which undo the rules before
+    /// them, then `$important` blocks, which exceptions don't undo. Last,
+    /// the pages of the site itself and its frames on the same domain are
+    /// let through, as navigations are. `None` if no filter can be
+    /// expressed.
+    #[cfg(any(target_os = "linux", target_os = "macos", test))]
+    pub fn content_rules(&self) -> Option<Arc<str>> {
+        self.content_rules
+            .get_or_init(|| {
+                let rule = |filter: &NetworkFilter, action: &str| {
+                    let trigger = filter.content_trigger()?;
+                    Some(serde_json::json!({ "trigger": trigger, "action": { "type": action } }))
+                };
+                let mut rules: Vec<serde_json::Value> = Vec::new();
+                rules.extend(self.block.iter().filter(|f| !f.important).filter_map(|f| rule(f, "block")));
+                rules.extend(self.allow.iter().filter_map(|f| rule(f, "ignore-previous-rules")));
+                rules.extend(self.block.iter().filter(|f| f.important).filter_map(|f| rule(f, "block")));
+                if rules.is_empty() {
+                    return None;
+                }
+                rules.push(serde_json::json!({
+                    "trigger": { "url-filter": ".*", "resource-type": ["document"], "load-type": ["first-party"] },
+                    "action": { "type": "ignore-previous-rules" },
+                }));
+                Some(serde_json::Value::from(rules).to_string().into())
+            })
+            .clone()
+    }
+
+    /// Stylesheet hiding the elements the cosmetic filters select on `host`.
+    /// One rule per selector, so a selector the engine rejects doesn't take
+    /// others down with it.
+    pub fn css_for(&self, host: &str) -> String {
+        let unhidden: HashSet<&str> = self
+            .unhide
+            .iter()
+            .filter(|filter| filter.applies_to(host))
+            .map(|filter| filter.selector.as_str())
+            .collect();
+        let mut seen = HashSet::new();
+        let mut css = String::new();
+        for filter in self.hide.iter().filter(|filter| filter.applies_to(host)) {
+            let selector = filter.selector.as_str();
+            if !unhidden.contains(selector) && seen.insert(selector) {
+                css.push_str(selector);
+                css.push_str(" { display: none !important; }\n");
+            }
+        }
+        css
+    }
+}
+
+/// Script that applies (or, with empty `css`, removes) the cosmetic
+/// stylesheet in a page. Safe to run repeatedly.
+const COSMETIC_SCRIPT: &str = r#"
+(function() {
+    'use strict';
+    var css = __CSS__;
+
+    function apply() {
+        var style = document.getElementById('peko-content-blocker');
+        if (!css) {
+            if (style) style.remove();
+            return;
+        }
+        if (!style) {
+            style = document.createElement('style');
+            style.id = 'peko-content-blocker';
+            (document.head || document.documentElement).appendChild(style);
+        }
+        if (style.textContent !== css) style.textContent = css;
+    }
+
+    if (document.documentElement) {
+        apply();
+    } else {
+        document.addEventListener('DOMContentLoaded', apply);
+    }
+})();
+"#;
+
+pub fn cosmetic_script(css: &str) -> String {
+    COSMETIC_SCRIPT.replace("__CSS__", &serde_json::Value::from(css).to_string())
+}
+
+/// Filter list paths may start with `~/`.
+fn expand_path(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Content blocker state shared by the site windows.
+#[derive(Default)]
+pub struct Blocker {
+    filters: Mutex<Arc<FilterSet>>,
+    lists: Mutex<Vec<ListStatus>>,
+    /// Sites with blocking turned off
+    disabled: Mutex<HashSet<String>>,
+    /// Requests blocked this session where Peko sees them, by site
+    blocked: Mutex<HashMap<String, u64>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BlockerStatus {
+    pub lists: Vec<ListStatus>,
+    pub blocked: HashMap<String, u64>,
+}
+
+impl Blocker {
+    /// Replace the filters with the lists at `paths`. A list that can't be
+    /// read is reported in its status and otherwise ignored.
+    pub fn load(&self, paths: &[String]) {
+        let mut filters = FilterSet::default();
+        let mut lists = Vec::new();
+        for path in paths {
+            let status = match fs::read_to_string(expand_path(path)) {
+                Ok(text) => filters.add_list(&text),
+                Err(e) => ListStatus { error: Some(format!("Failed to read filter list: {}", e)), ..Default::default() },
+            };
+            lists.push(ListStatus { path: path.clone(), ..status });
+        }
+        *self.filters.lock().unwrap() = Arc::new(filters);
+        *self.lists.lock().unwrap() = lists;
+    }
+
+    pub fn set_disabled(&self, sites: impl IntoIterator<Item = String>) {
+        *self.disabled.lock().unwrap() = sites.into_iter().collect();
+    }
+
+    fn enabled(&self, site: &str) -> bool {
+        !self.disabled.lock().unwrap().contains(site)
+    }
+
+    /// Whether a navigation of `site`'s window to `url` may go ahead.
+    /// `on_navigation` can't tell the page from its frames, so it is
+    /// checked as a frame.
+    pub fn allow_navigation(&self, site: &str, site_host: &str, url: &Url) -> bool {
+        self.allow_request(site, site_host, site_host, url, ResourceType::Subdocument)
+    }
+
+    /// Whether a request of a page on `page_host` in `site`'s window may go
+    /// ahead. Pages and frames within the site itself are always allowed,
+    /// as blocking them would break the site.
+    pub fn allow_request(
+        &self,
+        site: &str,
+        site_host: &str,
+        page_host: &str,
+        url: &Url,
+        resource_type: ResourceType,
+    ) -> bool {
+        if !matches!(url.scheme(), "http" | "https" | "ws" | "wss") || !self.enabled(site) {
+            return true;
+        }
+        let host = url.host_str().unwrap_or_default();
+        if matches!(resource_type, ResourceType::Subdocument | ResourceType::Document)
+            && base_domain(host) == base_domain(site_host)
+        {
+            return true;
+        }
+
+        let filters = self.filters.lock().unwrap().clone();
+        if !filters.should_block(&Request::new(url, page_host, resource_type)) {
+            return true;
+        }
+        *self.blocked.lock().unwrap().entry(site.to_string()).or_default() += 1;
+        log::debug!("Blocked {} in {}", url, site);
+        false
+    }
+
+    /// The filters as a WebKit content rule list, see [`FilterSet::content_rules`].
+    #[cfg(any(target_os = "linux", target_os = "macos", test))]
+    pub fn content_rules(&self) -> Option<Arc<str>> {
+        let filters = self.filters.lock().unwrap().clone();
+        filters.content_rules()
+    }
+
+    /// Cosmetic stylesheet for a page of `site` on `host`; empty when
+    /// blocking is off for the site.
+    pub fn css_for(&self, site: &str, host: &str) -> String {
+        if !self.enabled(site) {
+            return String::new();
+        }
+        self.filters.lock().unwrap().css_for(host)
+    }
+
+    pub fn status(&self) -> BlockerStatus {
+        BlockerStatus {
+            lists: self.lists.lock().unwrap().clone(),
+            blocked: self.blocked.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// Filter the requests of a new site window. `site_host` is the host of
+/// the site's URL.
+pub fn attach_request_filter(window: &tauri::WebviewWindow, site: &str, site_host: &str) -> Result<(), String> {
+    let app = window.app_handle().clone();
+    let (site, site_host) = (site.to_string(), site_host.to_string());
+    window
+        .with_webview(move |webview| platform::attach(webview, app, site, site_host))
+        .map_err(|e| format!("Failed to filter requests: {}", e))
+}
+
+/// Bring a window's request filtering up to date with the filter lists and
+/// its site's blocking setting.
+pub fn refresh_request_filter(window: &tauri::WebviewWindow, site: &str) -> Result<(), String> {
+    let app = window.app_handle().clone();
+    let site = site.to_string();
+    window
+        .with_webview(move |webview| platform::refresh(webview, app, site))
+        .map_err(|e| format!("Failed to filter requests: {}", e))
+}
+
+/// Identifier of the compiled content rule list in WebKit's store. There
+/// is one list, replaced when the filters change.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const RULE_LIST_ID: &str = "peko-content-blocker";
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{Blocker, RULE_LIST_ID};
+    use std::cell::RefCell;
+    use std::ffi::CString;
+    use std::sync::Arc;
+    use tauri::{AppHandle, Manager};
+    use webkit2gtk::glib::translate::{from_glib_full, ToGlibPtr};
+    use webkit2gtk::{ffi, gio, glib, UserContentManagerExt, WebView, WebViewExt};
+
+    /// The compiled rule list, or the webviews waiting for it to compile.
+    enum Compiled {
+        Ready(Arc<str>, *mut ffi::WebKitUserContentFilter),
+        Pending(Arc<str>, Vec<WebView>),
+    }
+
+    thread_local! {
+        // Only touched from `with_webview`, which runs on the main thread
+        static COMPILED: RefCell<Option<Compiled>> = const { RefCell::new(None) };
+    }
+
+    pub fn attach(webview: tauri::webview::PlatformWebview, app: AppHandle, site: String, _site_host: String) {
+        refresh(webview, app, site)
+    }
+
+    pub fn refresh(webview: tauri::webview::PlatformWebview, app: AppHandle, site: String) {
+        let webview = webview.inner();
+        let Some(manager) = webview.user_content_manager() else {
+            return;
+        };
+        manager.remove_all_filters();
+        COMPILED.with_borrow_mut(|compiled| {
+            if let Some(Compiled::Pending(_, waiting)) = compiled {
+                waiting.retain(|w| w != &webview);
+            }
+        });
+
+        let blocker = app.state::<Blocker>();
+        if !blocker.enabled(&site) {
+            return;
+        }
+        let Some(rules) = blocker.content_rules() else {
+            return;
+        };
+        COMPILED.with_borrow_mut(|compiled| match compiled {
+            Some(Compiled::Ready(current, filter)) if Arc::ptr_eq(current, &rules) => unsafe {
+                ffi::webkit_user_content_manager_add_filter(manager.to_glib_none().0, *filter);
+            },
+            Some(Compiled::Pending(current, waiting)) if Arc::ptr_eq(current, &rules) => waiting.push(webview),
+            _ => {
+                let Ok(dir) = app.path().app_data_dir() else {
+                    return;
+                };
+                let old = compiled.replace(Compiled::Pending(rules.clone(), vec![webview]));
+                if let Some(Compiled::Ready(_, filter)) = old {
+                    unsafe { ffi::webkit_user_content_filter_unref(filter) };
+                }
+                compile(&dir.join("content-filters"), rules);
+            }
+        });
+    }
+
+    fn compile(dir: &std::path::Path, rules: Arc<str>) {
+        let (Ok(dir), Ok(id)) = (CString::new(dir.to_string_lossy().as_bytes()), CString::new(RULE_LIST_ID)) else {
+            return;
+        };
+        let source = glib::Bytes::from(rules.as_bytes());
+        unsafe {
+            // The store is released once the list is saved
+            let store = ffi::webkit_user_content_filter_store_new(dir.as_ptr());
+            ffi::webkit_user_content_filter_store_save(
+                store,
+                id.as_ptr(),
+                source.to_glib_none().0,
+                std::ptr::null_mut(),
+                Some(saved),
+                Box::into_raw(Box::new(rules)) as glib::ffi::gpointer,
+            );
+        }
+    }
+
+    unsafe extern "C" fn saved(
+        store: *mut glib::gobject_ffi::GObject,
+        result: *mut gio::ffi::GAsyncResult,
+        rules: glib::ffi::gpointer,
+    ) {
+        let rules = *Box::from_raw(rules as *mut Arc<str>);
+        let mut error = std::ptr::null_mut();
+        let filter = ffi::webkit_user_content_filter_store_save_finish(store as *mut _, result, &mut error);
+        glib::gobject_ffi::g_object_unref(store);
+        if filter.is_null() {
+            let error: glib::Error = from_glib_full(error);
+            log::warn!("Failed to compile the filter lists: {}", error);
+        }
+
+        COMPILED.with_borrow_mut(|compiled| {
+            let waiting = match compiled.take() {
+                Some(Compiled::Pending(current, waiting)) if Arc::ptr_eq(&current, &rules) => waiting,
+                // The filters changed while this list compiled
+                other => {
+                    *compiled = other;
+                    if !filter.is_null() {
+                        ffi::webkit_user_content_filter_unref(filter);
+                    }
+                    return;
+                }
+            };
+            if filter.is_null() {
+                return;
+            }
+            for webview in waiting {
+                if let Some(manager) = webview.user_content_manager() {
+                    ffi::webkit_user_content_manager_add_filter(manager.to_glib_none().0, filter);
+                }
+            }
+            *compiled = Some(Compiled::Ready(rules, filter));
+        });
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{Blocker, RULE_LIST_ID};
+    use block2::RcBlock;
+    use objc2::{rc::Retained, MainThreadMarker};
+    use objc2_foundation::{NSError, NSString};
+    use objc2_web_kit::*;
+    use std::cell::RefCell;
+    use std::sync::Arc;
+    use tauri::{AppHandle, Manager};
+
+    /// The compiled rule list, or the webviews waiting for it to compile.
+    enum Compiled {
+        Ready(Arc<str>, Retained<WKContentRuleList>),
+        Pending(Arc<str>, Vec<Retained<WKWebView>>),
+    }
+
+    thread_local! {
+        // Only touched from `with_webview`, which runs on the main thread
+        static COMPILED: RefCell<Option<Compiled>> = const { RefCell::new(None) };
+    }
+
+    pub fn attach(webview: tauri::webview::PlatformWebview, app: AppHandle, site: String, _site_host: String) {
+        refresh(webview, app, site)
+    }
+
+    pub fn refresh(webview: tauri::webview::PlatformWebview, app: AppHandle, site: String) {
+        let mtm = MainThreadMarker::new().expect("not on the main thread");
+        let webview: Retained<WKWebView> = unsafe { Retained::retain(webview.inner().cast()) }.expect("no webview");
+        unsafe { webview.configuration().userContentController().removeAllContentRuleLists() };
+        COMPILED.with_borrow_mut(|compiled| {
+            if let Some(Compiled::Pending(_, waiting)) = compiled {
+                waiting.retain(|w| w != &webview);
+            }
+        });
+
+        let blocker = app.state::<Blocker>();
+        if !blocker.enabled(&site) {
+            return;
+        }
+        let Some(rules) = blocker.content_rules() else {
+            return;
+        };
+        COMPILED.with_borrow_mut(|compiled| match compiled {
+            Some(Compiled::Ready(current, list)) if Arc::ptr_eq(current, &rules) => unsafe {
+                webview.configuration().userContentController().addContentRuleList(list);
+            },
+            Some(Compiled::Pending(current, waiting)) if Arc::ptr_eq(current, &rules) => waiting.push(webview),
+            _ => {
+                *compiled = Some(Compiled::Pending(rules.clone(), vec![webview]));
+                compile(mtm, rules);
+            }
+        });
+    }
+
+    fn compile(mtm: MainThreadMarker, rules: Arc<str>) {
+        let Some(store) = (unsafe { WKContentRuleListStore::defaultStore(mtm) }) else {
+            return;
+        };
+        let source = NSString::from_str(&rules);
+        let on_compiled = RcBlock::new(move |list: *mut WKContentRuleList, error: *mut NSError| {
+            let list = unsafe { Retained::retain(list) };
+            if let Some(error) = unsafe { error.as_ref() } {
+                log::warn!("Failed to compile the filter lists: {}", error.localizedDescription());
+            }
+            COMPILED.with_borrow_mut(|compiled| {
+                let waiting = match compiled.take() {
+                    Some(Compiled::Pending(current, waiting)) if Arc::ptr_eq(&current, &rules) => waiting,
+                    // The filters changed while this list compiled
+                    other => {
+                        *compiled = other;
+                        return;
+                    }
+                };
+                let Some(list) = list else {
+                    return;
+                };
+                for webview in waiting {
+                    unsafe { webview.configuration().userContentController().addContentRuleList(&list) };
+                }
+                *compiled = Some(Compiled::Ready(rules.clone(), list));
+            });
+        });
+        unsafe {
+            store.compileContentRuleListForIdentifier_encodedContentRuleList_completionHandler(
+                Some(&NSString::from_str(RULE_LIST_ID)),
+                Some(&source),
+                Some(&on_compiled),
+            );
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{Blocker, ResourceType};
+    use tauri::{AppHandle, Manager};
+    use url::Url;
+    use webview2_com::Microsoft::Web::WebView2::Win32::*;
+    use webview2_com::{CoTaskMemPWSTR, WebResourceRequestedEventHandler};
+    use windows_core::{w, Interface, PWSTR};
+
+    fn resource_type(context: COREWEBVIEW2_WEB_RESOURCE_CONTEXT) -> ResourceType {
+        match context {
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_DOCUMENT => ResourceType::Subdocument,
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_STYLESHEET => ResourceType::Stylesheet,
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_IMAGE => ResourceType::Image,
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_MEDIA => ResourceType::Media,
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_FONT => ResourceType::Font,
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_SCRIPT => ResourceType::Script,
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_XML_HTTP_REQUEST | COREWEBVIEW2_WEB_RESOURCE_CONTEXT_FETCH => {
+                ResourceType::Xhr
+            }
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_WEBSOCKET => ResourceType::Websocket,
+            COREWEBVIEW2_WEB_RESOURCE_CONTEXT_PING => ResourceType::Ping,
+            _ => ResourceType::Other,
+        }
+    }
+
+    pub fn attach(webview: tauri::webview::PlatformWebview, app: AppHandle, site: String, site_host: String) {
+        if let Err(e) = add_handler(webview, app, site, site_host) {
+            log::warn!("Failed to filter requests: {}", e);
+        }
+    }
+
+    /// The handler checks the blocker on every request, so it is always
+    /// up to date.
+    pub fn refresh(_webview: tauri::webview::PlatformWebview, _app: AppHandle, _site: String) {}
+
+    fn add_handler(
+        webview: tauri::webview::PlatformWebview,
+        app: AppHandle,
+        site: String,
+        site_host: String,
+    ) -> windows_core::Result<()> {
+        unsafe {
+            let core = webview.controller().CoreWebView2()?;
+            // Requests of frames and workers only reach the handler with
+            // source kinds, where the runtime supports them
+            match core.cast::<ICoreWebView2_22>() {
+                Ok(core) => core.AddWebResourceRequestedFilterWithRequestSourceKinds(
+                    w!("*"),
+                    COREWEBVIEW2_WEB_RESOURCE_CONTEXT_ALL,
+                    COREWEBVIEW2_WEB_RESOURCE_REQUEST_SOURCE_KINDS_ALL,
+                )?,
+                Err(_) => core.AddWebResourceRequestedFilter(w!("*"), COREWEBVIEW2_WEB_RESOURCE_CONTEXT_ALL)?,
+            }
+            let environment = core.cast::<ICoreWebView2_2>()?.Environment()?;
+            let handler = WebResourceRequestedEventHandler::create(Box::new(move |sender, args| {
+                let (Some(sender), Some(args)) = (sender, args) else {
+                    return Ok(());
+                };
+                let mut uri = PWSTR::null();
+                args.Request()?.Uri(&mut uri)?;
+                let Ok(url) = Url::parse(&CoTaskMemPWSTR::from(uri).to_string()) else {
+                    return Ok(());
+                };
+                let mut context = COREWEBVIEW2_WEB_RESOURCE_CONTEXT_OTHER;
+                args.ResourceContext(&mut context)?;
+                let mut source = PWSTR::null();
+                sender.Source(&mut source)?;
+                let page = Url::parse(&CoTaskMemPWSTR::from(source).to_string()).ok();
+                let page_host = page.as_ref().and_then(Url::host_str).unwrap_or(&site_host);
+
+                let blocker = app.state::<Blocker>();
+                if !blocker.allow_request(&site, &site_host, page_host, &url, resource_type(context)) {
+                    let response = environment.CreateWebResourceResponse(None, 403, w!("Blocked"), w!(""))?;
+                    args.SetResponse(&response)?;
+                }
+                Ok(())
+            }));
+            let mut token = 0;
+            core.add_WebResourceRequested(&handler, &mut token)
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use tauri::AppHandle;
+
+    pub fn attach(_webview: tauri::webview::PlatformWebview, _app: AppHandle, _site: String, _site_host: String) {}
+
+    pub fn refresh(_webview: tauri::webview::PlatformWebview, _app: AppHandle, _site: String) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    const LIST: &str = r#"[Adblock Plus 2.0]
+! Title: Test list
+||ads.example.com^
+||tracker.net^$third-party
+/banner/*/img^$image
+|https://exact.example/path|
+||video-ads.com^$subdocument,domain=news.com|~sports.news.com
+@@||ads.example.com/allowed^
+||evil.com^$important
+@@||evil.com^
+/ads-[0-9]+/
+||redirected.com^$redirect=noop.js
+##.ad-banner
+##div[id^="sponsored"]
+news.com,~sports.news.com##.promo
+news.com#@#.ad-banner
+example.com#?#.post:has-text(Sponsored)
+example.com##+js(set-constant, x, 1)
+example.*##.entity
+"#;
+
+    fn blocked(filters: &FilterSet, url: &str, source: &str, resource_type: ResourceType) -> bool {
+        let url = Url::parse(url).unwrap();
+        filters.should_block(&Request::new(&url, source, resource_type))
+    }
+
+    fn filters() -> (FilterSet, ListStatus) {
+        let mut filters = FilterSet::default();
+        let status = filters.add_list(LIST);
+        (filters, status)
+    }
+
+    #[test]
+    fn test_parse_counts_supported_filters() {
+        let (_, status) = filters();
+        assert_eq!(status.network_filters, 8);
+        assert_eq!(status.cosmetic_filters, 4);
+        assert_eq!(status.skipped, 5);
+    }
+
+    #[test]
+    fn test_network_filters() {
+        use ResourceType::*;
+        let (f, _) = filters();
+
+        assert!(blocked(&f, "https://ads.example.com/x.js", "gemini.google.com", Subdocument));
+        assert!(blocked(&f, "https://sub.ads.example.com/", "gemini.google.com", Subdocument));
+        assert!(!blocked(&f, "https://notads.example.com/", "gemini.google.com", Subdocument));
+        assert!(!blocked(&f, "https://ads.example.com.evil/", "gemini.google.com", Subdocument));
+        // Exception
+        assert!(!blocked(&f, "https://ads.example.com/allowed/1", "gemini.google.com", Subdocument));
+
+        assert!(blocked(&f, "https://tracker.net/p", "gemini.google.com", Script));
+        assert!(!blocked(&f, "https://tracker.net/p", "www.tracker.net", Script));
+
+        assert!(blocked(&f, "https://cdn.x/banner/a/img?w=300", "a.com", Image));
+        assert!(blocked(&f, "https://cdn.x/banner/a/b/img", "a.com", Image));
+        assert!(!blocked(&f, "https://cdn.x/banner/a/img?w=300", "a.com", Subdocument));
+        assert!(!blocked(&f, "https://cdn.x/banner/a/imgx", "a.com", Image));
+
+        assert!(blocked(&f, "https://exact.example/path", "a.com", Other));
+        assert!(!blocked(&f, "https://exact.example/path2", "a.com", Other));
+
+        assert!(blocked(&f, "https://video-ads.com/v", "www.news.com", Subdocument));
+        assert!(!blocked(&f, "https://video-ads.com/v", "sports.news.com", Subdocument));
+        assert!(!blocked(&f, "https://video-ads.com/v", "other.com", Subdocument));
+        assert!(!blocked(&f, "https://video-ads.com/v", "www.news.com", Script));
+
+        // $important beats the exception
+        assert!(blocked(&f, "https://evil.com/", "a.com", Script));
+        // Popups and documents need explicit options
+        assert!(!blocked(&f, "https://ads.example.com/", "a.com", Popup));
+    }
+
+    #[test]
+    fn test_filter_index() {
+        use ResourceType::*;
+        let (f, _) = filters();
+        assert_eq!(f.block.other.len(), 0);
+        assert!(f.block.by_word.contains_key("example"));
+        assert!(f.block.by_word.contains_key("banner"));
+        assert!(f.block.by_word.contains_key("exact"));
+
+        // Unanchored patterns don't start on a word boundary, and options
+        // alone have no words
+        let mut f = FilterSet::default();
+        f.add_list("ads.example\n||cdn.net/*/track.gif$image\n$third-party,domain=a.com\n");
+        assert_eq!(f.block.other.len(), 2);
+        assert!(f.block.by_word.contains_key("track"));
+        assert!(blocked(&f, "https://myads.example.org/", "a.com", Script));
+        assert!(blocked(&f, "https://cdn.net/x/y/track.gif", "a.com", Image));
+        assert!(blocked(&f, "https://b.com/", "www.a.com", Script));
+        assert!(!blocked(&f, "https://b.com/", "c.com", Script));
+    }
+
+    #[test]
+    fn test_matching_does_not_backtrack() {
+        let mut f = FilterSet::default();
+        f.add_list(&format!("{}b|\n", "*a".repeat(30)));
+        let url = format!("https://x.com/{}", "a".repeat(20_000));
+        let start = std::time::Instant::now();
+        assert!(!blocked(&f, &url, "a.com", ResourceType::Script));
+        assert!(blocked(&f, &format!("{}b", url), "a.com", ResourceType::Script));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_content_rules() {
+        let (f, _) = filters();
+        let rules: serde_json::Value = serde_json::from_str(&f.content_rules().unwrap()).unwrap();
+        let rules = rules.as_array().unwrap();
+        let filter_of = |i: usize| rules[i]["trigger"]["url-filter"].as_str().unwrap();
+        let action_of = |i: usize| rules[i]["action"]["type"].as_str().unwrap();
+        let find = |filter: &str| (0..rules.len()).find(|&i| filter_of(i) == filter).unwrap();
+
+        let ads = find(r"^[a-z][a-z0-9.+-]*://([^/]*\.)?ads\.example\.com([^a-zA-Z0-9_.%-].*)?$");
+        assert_eq!(action_of(ads), "block");
+        assert!(rules[ads]["trigger"]["resource-type"].as_array().unwrap().contains(&"raw".into()));
+        assert!(!rules[ads]["trigger"]["resource-type"].as_array().unwrap().contains(&"popup".into()));
+
+        let tracker = find(r"^[a-z][a-z0-9.+-]*://([^/]*\.)?tracker\.net([^a-zA-Z0-9_.%-].*)?$");
+        assert_eq!(rules[tracker]["trigger"]["load-type"], serde_json::json!(["third-party"]));
+
+        let banner = find(r"/banner/.*/img([^a-zA-Z0-9_.%-].*)?$");
+        assert_eq!(rules[banner]["trigger"]["resource-type"], serde_json::json!(["image"]));
+        assert_eq!(action_of(find(r"^https://exact\.example/path$")), "block");
+
+        // Exceptions come after the blocks they undo, important blocks after them
+        let allowed = find(r"^[a-z][a-z0-9.+-]*://([^/]*\.)?ads\.example\.com/allowed([^a-zA-Z0-9_.%-].*)?$");
+        assert_eq!(action_of(allowed), "ignore-previous-rules");
+        assert!(ads < allowed);
+        let evil = r"^[a-z][a-z0-9.+-]*://([^/]*\.)?evil\.com([^a-zA-Z0-9_.%-].*)?$";
+        let evil: Vec<usize> = (0..rules.len()).filter(|&i| filter_of(i) == evil).collect();
+        assert_eq!(evil.len(), 2);
+        assert_eq!((action_of(evil[0]), action_of(evil[1])), ("ignore-previous-rules", "block"));
+        assert_eq!(action_of(rules.len() - 1), "ignore-previous-rules");
+
+        // Included and excluded page domains together can't be expressed
+        assert!(!rules.iter().any(|rule| rule["trigger"]["url-filter"].as_str().unwrap().contains("video-ads")));
+
+        let mut f = FilterSet::default();
+        f.add_list("||a.com/x?y=(1)$domain=b.com|c.com,match-case\n");
+        let rules: serde_json::Value = serde_json::from_str(&f.content_rules().unwrap()).unwrap();
+        assert_eq!(rules[0]["trigger"]["url-filter"], r"^[a-z][a-z0-9.+-]*://([^/]*\.)?a\.com/x\?y=\(1\)");
+        assert_eq!(rules[0]["trigger"]["if-domain"], serde_json::json!(["*b.com", "*c.com"]));
+        assert_eq!(rules[0]["trigger"]["url-filter-is-case-sensitive"], true);
+        assert!(FilterSet::default().content_rules().is_none());
+    }
+
+    #[test]
+    fn test_cosmetic_css() {
+        let (f, _) = filters();
+        let css = f.css_for("www.news.com");
+        assert!(!css.contains(".ad-banner"));
+        assert!(css.contains("div[id^=\"sponsored\"] { display: none !important; }"));
+        assert!(css.contains(".promo"));
+
+        // The exception covers subdomains too
+        let css = f.css_for("sports.news.com");
+        assert!(!css.contains(".ad-banner"));
+        assert!(!css.contains(".promo"));
+
+        let css = f.css_for("example.com");
+        assert!(css.contains(".ad-banner"));
+        assert!(!css.contains("has-text") && !css.contains(".entity"));
+
+        let script = cosmetic_script("a { display: none; }\n</style>");
+        assert!(script.contains(r#"var css = "a { display: none; }\n</style>";"#));
+    }
+
+    #[test]
+    fn test_blocker_navigation() {
+        let dir = TempDir::new("blocker");
+        let path = dir.join("list.txt");
+        fs::write(&path, LIST).unwrap();
+
+        let blocker = Blocker::default();
+        blocker.load(&[path.to_string_lossy().into_owned(), dir.join("missing.txt").to_string_lossy().into_owned()]);
+        let status = blocker.status();
+        assert_eq!(status.lists[0].network_filters, 8);
+        assert!(status.lists[1].error.is_some());
+
+        let ad = Url::parse("https://ads.example.com/frame").unwrap();
+        assert!(!blocker.allow_navigation("gemini", "gemini.google.com", &ad));
+        assert!(!blocker.allow_navigation("gemini", "gemini.google.com", &ad));
+        // Never blocks the site itself
+        assert!(blocker.allow_navigation("ads", "www.example.com", &ad));
+        assert!(blocker.allow_navigation("gemini", "gemini.google.com", &Url::parse("about:blank").unwrap()));
+        assert_eq!(blocker.status().blocked.get("gemini"), Some(&2));
+
+        // Subresources of the site itself can be blocked, its pages can't
+        let first_party = Url::parse("https://ads.example.com/ad.js").unwrap();
+        assert!(!blocker.allow_request("ads", "www.example.com", "www.example.com", &first_party, ResourceType::Script));
+        assert!(blocker.allow_request("ads", "www.example.com", "www.example.com", &ad, ResourceType::Document));
+        assert!(!blocker.allow_request("gemini", "gemini.google.com", "gemini.google.com", &first_party, ResourceType::Image));
+        assert_eq!(blocker.status().blocked.get("gemini"), Some(&3));
+        assert!(blocker.content_rules().is_some());
+
+        blocker.set_disabled(["gemini".to_string()]);
+        assert!(blocker.allow_request("gemini", "gemini.google.com", "gemini.google.com", &first_party, ResourceType::Script));
+        assert!(blocker.allow_navigation("gemini", "gemini.google.com", &ad));
+        assert!(blocker.css_for("gemini", "gemini.google.com").is_empty());
+        assert!(!blocker.css_for("notebooklm", "notebooklm.google.com").is_empty());
+
+        assert_eq!(base_domain("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(base_domain("gemini.google.com"), "google.com");
+        assert_eq!(base_domain("localhost"), "localhost");
+    }
+}
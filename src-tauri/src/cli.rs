@@ -0,0 +1,519 @@
+//! Command-line interface: `peko <command> [args]`.
+//!
+//! Commands are forwarded to a running instance over the control socket when
+//! one is listening; otherwise they act on `settings.json` directly.
+
+use std::path::{Path, PathBuf};
+
+use crate::control::{self, ControlRequest, SiteList};
+use crate::deeplink;
+use crate::instance::{self, InstanceLock};
+
+const USAGE: &str = "Usage: peko [command | url | peko://link]
+
+Commands:
+  open <site-id> [--url <url>]             Show a site (launches Peko if needed)
+  list                                     List configured sites
+  add --name <name> --url <url> [--emoji <emoji>] [--id <id>]
+                                           Add a site
+  notes append <text>                      Append text to notes (\"-\" reads stdin)
+  settings export [--output <file>]        Print settings as JSON
+  run <action-id>                          Run a menu action in the running app
+  actions                                  List action ids and their shortcuts
+  help                                     Show this message
+  version                                  Show the version
+
+Without a command, Peko starts, or focuses the window of the instance that
+is already running. A web URL opens in the site with the same host.";
+
+/// What `main` should do after the CLI has run.
+pub enum Outcome {
+    Exit(i32),
+    Launch(LaunchOptions),
+}
+
+/// Options for starting the GUI.
+#[derive(Default)]
+pub struct LaunchOptions {
+    /// Show this site instead of the default one.
+    pub tab: Option<String>,
+    /// Navigate to this URL after startup.
+    pub url: Option<String>,
+    /// Proof that this process is the only running instance.
+    pub instance_lock: Option<InstanceLock>,
+}
+
+#[derive(Debug, PartialEq)]
+enum CliCommand {
+    Help,
+    Version,
+    /// Start the GUI, or hand the arguments to the running instance
+    Launch { site: Option<String>, url: Option<String> },
+    Request(ControlRequest),
+    /// A `peko://` link other than `open`; shows Peko once it has run
+    DeepLink(ControlRequest),
+    /// `settings export` with an optional output file
+    ExportSettings(Option<PathBuf>),
+    ListActions,
+}
+
+fn take_value(args: &mut std::slice::Iter<String>, flag: &str) -> Result<String, String> {
+    args.next().cloned().ok_or_else(|| format!("{} requires a value", flag))
+}
+
+/// Parse arguments (without the program name). `Ok(None)` means there is no
+/// CLI command and the GUI should start as usual.
+fn parse(args: &[String]) -> Result<Option<CliCommand>, String> {
+    let mut iter = args.iter();
+    let command = match iter.next() {
+        Some(command) => command.as_str(),
+        None => return Ok(None),
+    };
+
+    let parsed = match command {
+        "help" | "--help" | "-h" => CliCommand::Help,
+        "version" | "--version" | "-V" => CliCommand::Version,
+        "open" => {
+            let site = iter.next().ok_or("open requires a site id")?.clone();
+            let url = match iter.next().map(String::as_str) {
+                Some("--url") => Some(take_value(&mut iter, "--url")?),
+                Some(other) => return Err(format!("Unknown option for open: {}", other)),
+                None => None,
+            };
+            CliCommand::Launch { site: Some(site), url }
+        }
+        url if url.starts_with("https://") || url.starts_with("http://") => {
+            CliCommand::Launch { site: None, url: Some(url.to_string()) }
+        }
+        link if deeplink::is_deep_link(link) => match deeplink::parse(link)? {
+            ControlRequest::Activate { site, url } => CliCommand::Launch { site, url },
+            request => CliCommand::DeepLink(request),
+        },
+        "list" => CliCommand::Request(ControlRequest::List),
+        "run" => {
+            let action = iter.next().ok_or("run requires an action id")?;
+            if crate::actions::find(action).is_none() {
+                return Err(format!("Unknown action: {} (see `peko actions`)", action));
+            }
+            CliCommand::Request(ControlRequest::Run { action: action.clone() })
+        }
+        "actions" => CliCommand::ListActions,
+        "add" => {
+            let (mut name, mut url, mut emoji, mut id) = (None, None, None, None);
+            while let Some(flag) = iter.next() {
+                match flag.as_str() {
+                    "--name" => name = Some(take_value(&mut iter, flag)?),
+                    "--url" => url = Some(take_value(&mut iter, flag)?),
+                    "--emoji" => emoji = Some(take_value(&mut iter, flag)?),
+                    "--id" => id = Some(take_value(&mut iter, flag)?),
+                    other => return Err(format!("Unknown option for add: {}", other)),
+                }
+            }
+            CliCommand::Request(ControlRequest::Add {
+                name: name.ok_or("add requires --name")?,
+                url: url.ok_or("add requires --url")?,
+                emoji,
+                id,
+            })
+        }
+        "notes" => match iter.next().map(String::as_str) {
+            Some("append") => {
+                let text: Vec<&str> = iter.by_ref().map(String::as_str).collect();
+                if text.is_empty() {
+                    return Err("notes append requires text".to_string());
+                }
+                CliCommand::Request(ControlRequest::NotesAppend { text: text.join(" ") })
+            }
+            _ => return Err("Usage: peko notes append <text>".to_string()),
+        },
+        "settings" => match iter.next().map(String::as_str) {
+            Some("export") => {
+                let output = match iter.next().map(String::as_str) {
+                    Some("--output") | Some("-o") => Some(PathBuf::from(take_value(&mut iter, "--output")?)),
+                    Some(other) => return Err(format!("Unknown option for settings export: {}", other)),
+                    None => None,
+                };
+                CliCommand::ExportSettings(output)
+            }
+            _ => return Err("Usage: peko settings export [--output <file>]".to_string()),
+        },
+        // Anything else (e.g. macOS `-psn_*` launch arguments) starts the GUI
+        _ => return Ok(None),
+    };
+
+    if iter.next().is_some() {
+        return Err(format!("Too many arguments for {}", command));
+    }
+    Ok(Some(parsed))
+}
+
+/// Release builds on Windows are GUI programs without a console of their
+/// own, so output goes to the console `peko` was run from, if any.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails when started from Explorer, or when there is a console already,
+    // and then there's nothing to do
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// Entry point used by `main.rs`.
+pub fn main() -> Outcome {
+    #[cfg(windows)]
+    attach_console();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let command = match parse(&args) {
+        Ok(Some(command)) => command,
+        Ok(None) => CliCommand::Launch { site: None, url: None },
+        Err(e) => {
+            eprintln!("peko: {}\n\n{}", e, USAGE);
+            return Outcome::Exit(2);
+        }
+    };
+
+    let data_dir = match crate::app_data_dir_offline() {
+        Some(dir) => dir,
+        None if matches!(command, CliCommand::Launch { .. }) => {
+            return Outcome::Launch(LaunchOptions::default());
+        }
+        None => {
+            eprintln!("peko: could not determine the app data directory");
+            return Outcome::Exit(1);
+        }
+    };
+
+    match execute(command, &data_dir) {
+        Ok(Outcome::Launch(options)) => launch_or_forward(options, &data_dir),
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("peko: {}", e);
+            Outcome::Exit(1)
+        }
+    }
+}
+
+/// Become the primary instance, or pass the launch on to the one that is
+/// already running so it can focus the right tab.
+fn launch_or_forward(options: LaunchOptions, data_dir: &Path) -> Outcome {
+    match instance::try_acquire(data_dir) {
+        Ok(Some(lock)) => {
+            return Outcome::Launch(LaunchOptions { instance_lock: Some(lock), ..options });
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("peko: could not check for a running instance: {}", e);
+            return Outcome::Launch(options);
+        }
+    }
+
+    let request = ControlRequest::Activate { site: options.tab, url: options.url };
+    // The running instance may still be starting up and not listening yet
+    for _ in 0..20 {
+        match control::send(data_dir, &request) {
+            Ok(Some(response)) => {
+                return match response_result(response) {
+                    Ok(_) => Outcome::Exit(0),
+                    Err(e) => {
+                        eprintln!("peko: {}", e);
+                        Outcome::Exit(1)
+                    }
+                };
+            }
+            Ok(None) => std::thread::sleep(std::time::Duration::from_millis(250)),
+            Err(e) => {
+                eprintln!("peko: {}", e);
+                return Outcome::Exit(1);
+            }
+        }
+    }
+
+    eprintln!("peko: another instance is running but not responding");
+    Outcome::Exit(1)
+}
+
+fn execute(command: CliCommand, data_dir: &Path) -> Result<Outcome, String> {
+    let request = match command {
+        CliCommand::Help => {
+            println!("{}", USAGE);
+            return Ok(Outcome::Exit(0));
+        }
+        CliCommand::Version => {
+            println!("peko {}", env!("CARGO_PKG_VERSION"));
+            return Ok(Outcome::Exit(0));
+        }
+        CliCommand::ExportSettings(output) => {
+            let settings = match control::send(data_dir, &ControlRequest::SettingsExport)? {
+                Some(response) => response_result(response)?,
+                None => crate::public_settings(&crate::read_settings_file(&crate::settings_file(data_dir)))?,
+            };
+            let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+            match output {
+                Some(path) => std::fs::write(&path, json + "\n")
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?,
+                None => println!("{}", json),
+            }
+            return Ok(Outcome::Exit(0));
+        }
+        CliCommand::ListActions => {
+            let settings = crate::read_settings_file(&crate::settings_file(data_dir));
+            let keymap = crate::keymap::Keymap::new(&settings.keymap);
+            for command in crate::actions::COMMANDS {
+                println!("{}\t{}\t{}", command.id, command.label, keymap.get(command.id).unwrap_or("-"));
+            }
+            return Ok(Outcome::Exit(0));
+        }
+        // Validated here; `main` decides whether to start or forward
+        CliCommand::Launch { site, url } => {
+            let launch = run_offline(&ControlRequest::Activate { site, url }, data_dir)?;
+            return Ok(Outcome::Launch(launch.unwrap_or_default()));
+        }
+        CliCommand::DeepLink(request) => {
+            if let Some(response) = control::send(data_dir, &request)? {
+                response_result(response)?;
+                return Ok(Outcome::Exit(0));
+            }
+            let launch = run_offline(&request, data_dir)?;
+            return Ok(Outcome::Launch(launch.unwrap_or_default()));
+        }
+        CliCommand::Request(request) => request,
+    };
+
+    // Text from stdin for `notes append -`
+    let request = match request {
+        ControlRequest::NotesAppend { text } if text == "-" => {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)
+                .map_err(|e| format!("Failed to read stdin: {}", e))?;
+            ControlRequest::NotesAppend { text }
+        }
+        request => request,
+    };
+
+    let result = match control::send(data_dir, &request)? {
+        Some(response) => response_result(response)?,
+        None => match run_offline(&request, data_dir)? {
+            Some(launch) => return Ok(Outcome::Launch(launch)),
+            None => serde_json::Value::Null,
+        },
+    };
+
+    print_result(&request, result);
+    Ok(Outcome::Exit(0))
+}
+
+fn response_result(response: control::ControlResponse) -> Result<serde_json::Value, String> {
+    if response.ok {
+        Ok(response.result)
+    } else {
+        Err(response.error.unwrap_or_else(|| "Unknown error".to_string()))
+    }
+}
+
+/// Apply a request to the settings file when Peko isn't running.
+/// Returns launch options when the request should start the GUI.
+fn run_offline(request: &ControlRequest, data_dir: &Path) -> Result<Option<LaunchOptions>, String> {
+    // A running instance would overwrite the change with its own settings
+    let _lock = match request {
+        ControlRequest::Add { .. } | ControlRequest::NotesAppend { .. } | ControlRequest::NotesToggle => {
+            Some(lock_for_offline_write(data_dir)?)
+        }
+        _ => None,
+    };
+    let path = crate::settings_file(data_dir);
+    let mut settings = crate::read_settings_file(&path);
+
+    match request {
+        ControlRequest::Activate { site, url } => {
+            let parsed = url.as_deref().map(instance::parse_web_url).transpose()?;
+            instance::target_site(&settings.websites, site.as_deref(), parsed.as_ref())?;
+            return Ok(Some(LaunchOptions {
+                tab: site.clone(),
+                url: url.clone(),
+                instance_lock: None,
+            }));
+        }
+        ControlRequest::List => {
+            let list = SiteList { websites: settings.websites, active_tab: settings.active_tab };
+            print_sites(&list);
+            return Ok(None);
+        }
+        ControlRequest::Add { name, url, emoji, id } => {
+            let mut website = crate::new_website(&settings.websites, name, url, emoji.clone(), id.clone())?;
+            // Only the new site: older ones get theirs, with their data, at startup
+            crate::profiles::assign(std::slice::from_mut(&mut website), &mut settings.profiles, false);
+            settings.websites.push(website);
+            crate::validate_websites(&settings.websites)?;
+            crate::write_settings_file(&path, &settings)?;
+        }
+        ControlRequest::NotesAppend { text } => {
+            let entry = format!("{}\n", text.trim_end());
+            settings.notes_content = crate::clip::append_entry(&settings.notes_content, &entry);
+            crate::write_settings_file(&path, &settings)?;
+        }
+        ControlRequest::NotesToggle => {
+            settings.notes_mode = settings.notes_mode.next();
+            crate::write_settings_file(&path, &settings)?;
+            return Ok(Some(LaunchOptions::default()));
+        }
+        ControlRequest::SettingsExport => {}
+        ControlRequest::Run { .. } => return Err("Peko is not running".to_string()),
+    }
+    Ok(None)
+}
+
+/// Take the instance lock for the length of an offline write, so it can't
+/// race an instance that is running but didn't answer on the control socket.
+fn lock_for_offline_write(data_dir: &Path) -> Result<InstanceLock, String> {
+    match instance::try_acquire(data_dir) {
+        Ok(Some(lock)) => Ok(lock),
+        Ok(None) => Err("Peko is running but not responding; try again once it has started".to_string()),
+        Err(e) => Err(format!("Failed to check for a running instance: {}", e)),
+    }
+}
+
+fn print_sites(list: &SiteList) {
+    for website in &list.websites {
+        let marker = if website.id == list.active_tab { "*" } else { " " };
+        println!("{} {}\t{} {}\t{}", marker, website.id, website.emoji, website.name, website.url);
+    }
+}
+
+fn print_result(request: &ControlRequest, result: serde_json::Value) {
+    match request {
+        ControlRequest::List => {
+            if let Ok(list) = serde_json::from_value::<SiteList>(result) {
+                print_sites(&list);
+            }
+        }
+        ControlRequest::Add { .. } => {
+            if let Some(id) = result.get("id").and_then(|id| id.as_str()) {
+                println!("Added {}", id);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&args(&[])), Ok(None));
+        assert_eq!(parse(&args(&["-psn_0_12345"])), Ok(None));
+        assert_eq!(
+            parse(&args(&["open", "gemini"])),
+            Ok(Some(CliCommand::Launch { site: Some("gemini".to_string()), url: None }))
+        );
+        assert_eq!(
+            parse(&args(&["open", "gemini", "--url", "https://gemini.google.com/app/1"])),
+            Ok(Some(CliCommand::Launch {
+                site: Some("gemini".to_string()),
+                url: Some("https://gemini.google.com/app/1".to_string()),
+            }))
+        );
+        assert_eq!(
+            parse(&args(&["https://example.com"])),
+            Ok(Some(CliCommand::Launch { site: None, url: Some("https://example.com".to_string()) }))
+        );
+        assert_eq!(
+            parse(&args(&["peko://open/gemini"])),
+            Ok(Some(CliCommand::Launch { site: Some("gemini".to_string()), url: None }))
+        );
+        assert_eq!(
+            parse(&args(&["peko://notes/toggle"])),
+            Ok(Some(CliCommand::DeepLink(ControlRequest::NotesToggle)))
+        );
+        assert!(parse(&args(&["peko://settings/export"])).is_err());
+        assert_eq!(
+            parse(&args(&["run", "toggle_notes"])),
+            Ok(Some(CliCommand::Request(ControlRequest::Run { action: "toggle_notes".to_string() })))
+        );
+        assert_eq!(parse(&args(&["actions"])), Ok(Some(CliCommand::ListActions)));
+        assert_eq!(
+            parse(&args(&["notes", "append", "hello", "world"])),
+            Ok(Some(CliCommand::Request(ControlRequest::NotesAppend { text: "hello world".to_string() })))
+        );
+        assert_eq!(
+            parse(&args(&["settings", "export", "-o", "out.json"])),
+            Ok(Some(CliCommand::ExportSettings(Some(PathBuf::from("out.json")))))
+        );
+    }
+
+    #[test]
+    fn test_parse_add() {
+        assert_eq!(
+            parse(&args(&["add", "--name", "Docs", "--url", "https://docs.rs", "--emoji", "📚"])),
+            Ok(Some(CliCommand::Request(ControlRequest::Add {
+                name: "Docs".to_string(),
+                url: "https://docs.rs".to_string(),
+                emoji: Some("📚".to_string()),
+                id: None,
+            })))
+        );
+        assert!(parse(&args(&["add", "--name", "Docs"])).is_err());
+        assert!(parse(&args(&["add", "--url"])).is_err());
+        assert!(parse(&args(&["add", "--bogus", "x"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_usage() {
+        assert!(parse(&args(&["open"])).is_err());
+        assert!(parse(&args(&["open", "a", "b"])).is_err());
+        assert!(parse(&args(&["notes"])).is_err());
+        assert!(parse(&args(&["run"])).is_err());
+        assert!(parse(&args(&["run", "launch_missiles"])).is_err());
+        assert!(parse(&args(&["notes", "append"])).is_err());
+        assert!(parse(&args(&["settings", "import"])).is_err());
+    }
+
+    #[test]
+    fn test_offline_add_and_append() {
+        let dir = TempDir::new("cli");
+
+        let add = ControlRequest::Add {
+            name: "Docs".to_string(),
+            url: "https://docs.rs".to_string(),
+            emoji: None,
+            id: None,
+        };
+        assert!(run_offline(&add, &dir).unwrap().is_none());
+        run_offline(&ControlRequest::NotesAppend { text: "first".to_string() }, &dir).unwrap();
+        run_offline(&ControlRequest::NotesAppend { text: "second".to_string() }, &dir).unwrap();
+
+        let settings = crate::read_settings_file(&crate::settings_file(&dir));
+        assert_eq!(settings.websites.len(), 3);
+        assert_eq!(settings.websites[2].id, "docs");
+        assert_eq!(settings.notes_content, "first\n\nsecond\n");
+
+        let open = |site: &str| ControlRequest::Activate { site: Some(site.to_string()), url: None };
+        let launch = run_offline(&open("docs"), &dir).unwrap();
+        assert_eq!(launch.and_then(|o| o.tab).as_deref(), Some("docs"));
+        assert!(run_offline(&open("missing"), &dir).is_err());
+        let open_url = |url: &str| ControlRequest::Activate { site: None, url: Some(url.to_string()) };
+        let launch = run_offline(&open_url("https://docs.rs/serde"), &dir).unwrap();
+        assert_eq!(launch.and_then(|o| o.tab), None);
+        assert!(run_offline(&open_url("https://example.com/"), &dir).is_err());
+
+        // Not while an instance holds the lock, even if it didn't answer
+        let running = instance::try_acquire(&dir).unwrap();
+        assert!(run_offline(&ControlRequest::NotesAppend { text: "third".to_string() }, &dir).is_err());
+        drop(running);
+        assert_eq!(crate::read_settings_file(&crate::settings_file(&dir)).notes_content, "first\n\nsecond\n");
+    }
+}
@@ -0,0 +1,209 @@
+//! "Clip to Notes": capture the active tab's selection into the notes document.
+//! "Send Selection To" uses the same capture to type the selection into
+//! another site.
+//!
+//! The site window can't return values from `eval`, so the injected script
+//! calls back into the `clip_to_notes` command. Each clip request carries a
+//! one-time token so pages can't append to notes or type into other sites
+//! on their own.
+
+use serde::Deserialize;
+
+/// Menu item ids of the Send Selection To submenu are this prefix followed
+/// by the target site id.
+pub const SEND_MENU_PREFIX: &str = "send_to:";
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipFormat {
+    Markdown,
+    Text,
+}
+
+/// Where a captured selection goes.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ClipDestination {
+    Notes,
+    /// Typed into the main input of this site
+    Site(String),
+}
+
+/// Script evaluated in the site window. `__TOKEN__` and `__FORMAT__` are
+/// replaced with JSON string literals before evaluation.
+const CLIP_SCRIPT: &str = r#"
+(function() {
+    'use strict';
+    var token = __TOKEN__;
+    var format = __FORMAT__;
+
+    function toMarkdown(node) {
+        if (node.nodeType === Node.TEXT_NODE) {
+            return node.textContent.replace(/\s+/g, ' ');
+        }
+        if (node.nodeType !== Node.ELEMENT_NODE && node.nodeType !== Node.DOCUMENT_FRAGMENT_NODE) {
+            return '';
+        }
+        var inner = Array.prototype.map.call(node.childNodes, toMarkdown).join('');
+        switch (node.nodeName) {
+            case 'H1': return '\n# ' + inner.trim() + '\n\n';
+            case 'H2': return '\n## ' + inner.trim() + '\n\n';
+            case 'H3': return '\n### ' + inner.trim() + '\n\n';
+            case 'H4': case 'H5': case 'H6': return '\n#### ' + inner.trim() + '\n\n';
+            case 'P': case 'DIV': case 'SECTION': case 'ARTICLE': return '\n' + inner.trim() + '\n\n';
+            case 'BR': return '\n';
+            case 'STRONG': case 'B': return inner.trim() ? '**' + inner.trim() + '**' : '';
+            case 'EM': case 'I': return inner.trim() ? '*' + inner.trim() + '*' : '';
+            case 'CODE': return node.closest && node.closest('pre') ? node.textContent : '`' + inner + '`';
+            case 'PRE': return '\n```\n' + node.textContent.replace(/\n$/, '') + '\n```\n\n';
+            case 'A':
+                var href = node.getAttribute('href');
+                if (!href) return inner;
+                try { href = new URL(href, location.href).href; } catch (e) {}
+                return '[' + inner.trim() + '](' + href + ')';
+            case 'LI':
+                var parent = node.parentNode;
+                var prefix = parent && parent.nodeName === 'OL'
+                    ? (Array.prototype.indexOf.call(parent.children, node) + 1) + '. '
+                    : '- ';
+                return prefix + inner.trim() + '\n';
+            case 'UL': case 'OL': return '\n' + inner + '\n';
+            case 'BLOCKQUOTE':
+                return '\n' + inner.trim().split('\n').map(function(l) { return '> ' + l; }).join('\n') + '\n\n';
+            case 'SCRIPT': case 'STYLE': case 'NOSCRIPT': return '';
+            default: return inner;
+        }
+    }
+
+    var selection = window.getSelection();
+    var text = '';
+    if (selection && selection.rangeCount > 0 && !selection.isCollapsed) {
+        if (format === 'markdown') {
+            var fragment = document.createDocumentFragment();
+            for (var i = 0; i < selection.rangeCount; i++) {
+                fragment.appendChild(selection.getRangeAt(i).cloneContents());
+            }
+            text = toMarkdown(fragment).replace(/\n{3,}/g, '\n\n').trim();
+        }
+        if (!text) {
+            text = selection.toString().trim();
+        }
+    }
+
+    window.__TAURI_INTERNALS__.invoke('clip_to_notes', {
+        token: token,
+        text: text,
+        title: document.title,
+        url: location.href
+    }).catch(function(e) { console.error('[Peko] Clip failed:', e); });
+})();
+"#;
+
+pub fn clip_script(token: &str, format: ClipFormat) -> String {
+    let format = match format {
+        ClipFormat::Markdown => "markdown",
+        ClipFormat::Text => "text",
+    };
+    CLIP_SCRIPT
+        .replace("__TOKEN__", &serde_json::Value::from(token).to_string())
+        .replace("__FORMAT__", &serde_json::Value::from(format).to_string())
+}
+
+/// Format a clipped selection as a Markdown block with a source link.
+pub fn format_clip(text: &str, title: &str, url: &str, timestamp: &str) -> String {
+    let title = title.trim();
+    let title = if title.is_empty() { url } else { title };
+    // Keep the link text from breaking out of the Markdown link syntax
+    let title: String = title
+        .chars()
+        .map(|c| match c {
+            '[' => '(',
+            ']' => ')',
+            '\n' | '\r' => ' ',
+            c => c,
+        })
+        .collect();
+
+    let is_web_url = url::Url::parse(url)
+        .map(|u| u.scheme() == "https" || u.scheme() == "http")
+        .unwrap_or(false);
+    let source = if is_web_url {
+        format!("[{}](<{}>)", title, url)
+    } else {
+        title
+    };
+
+    format!("> Clipped from {} — {}\n\n{}\n", source, timestamp, text.trim())
+}
+
+/// Take the pending request (window label, token, payload) if the callback
+/// came from its window with its token. Anything else leaves it in place, so
+/// another page can't cancel a request by calling back with a wrong token.
+pub fn take_matching<T>(pending: &mut Option<(String, String, T)>, label: &str, token: &str) -> Option<T> {
+    match pending {
+        Some((expected_label, expected_token, _)) if expected_label == label && expected_token == token => {
+            pending.take().map(|(_, _, payload)| payload)
+        }
+        _ => None,
+    }
+}
+
+/// Append an entry to the notes, separated from existing content by a blank line.
+pub fn append_entry(notes: &str, entry: &str) -> String {
+    let existing = notes.trim_end();
+    if existing.is_empty() {
+        entry.to_string()
+    } else {
+        format!("{}\n\n{}", existing, entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_clip_links_source() {
+        let entry = format_clip(
+            "  Some **answer**  ",
+            "Gemini [beta]",
+            "https://gemini.google.com/app/123",
+            "2026-01-02 03:04",
+        );
+        assert_eq!(
+            entry,
+            "> Clipped from [Gemini (beta)](<https://gemini.google.com/app/123>) — 2026-01-02 03:04\n\nSome **answer**\n"
+        );
+    }
+
+    #[test]
+    fn test_format_clip_rejects_non_web_urls() {
+        let entry = format_clip("text", "Evil", "javascript:alert(1)", "now");
+        assert!(entry.starts_with("> Clipped from Evil — now"));
+        assert!(!entry.contains("javascript:"));
+    }
+
+    #[test]
+    fn test_append_entry_separates_with_blank_line() {
+        assert_eq!(append_entry("", "entry\n"), "entry\n");
+        assert_eq!(append_entry("notes\n\n\n", "entry\n"), "notes\n\nentry\n");
+    }
+
+    #[test]
+    fn test_take_matching_keeps_request_on_mismatch() {
+        let mut pending = Some(("gemini".to_string(), "secret".to_string(), ClipDestination::Notes));
+        assert_eq!(take_matching(&mut pending, "other", "secret"), None);
+        assert_eq!(take_matching(&mut pending, "gemini", "junk"), None);
+        assert!(pending.is_some());
+
+        assert_eq!(take_matching(&mut pending, "gemini", "secret"), Some(ClipDestination::Notes));
+        assert!(pending.is_none());
+        assert_eq!(take_matching(&mut pending, "gemini", "secret"), None);
+    }
+
+    #[test]
+    fn test_clip_script_embeds_escaped_token() {
+        let script = clip_script("abc\"def", ClipFormat::Text);
+        assert!(script.contains(r#"var token = "abc\"def";"#));
+        assert!(script.contains(r#"var format = "text";"#));
+    }
+}
@@ -0,0 +1,284 @@
+//! Clipboard history: recently copied text, most recent first, kept in
+//! memory while the feature is on.
+//!
+//! With persistence on it is saved to `clipboard_history.bin`, encrypted
+//! with ChaCha20-Poly1305 under a random key kept in the OS keychain
+//! (Keychain on macOS, Credential Manager on Windows, the Secret Service on
+//! Linux), never next to the file.
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Keychain entry holding the history key.
+const KEYCHAIN_SERVICE: &str = "com.peko.desktop";
+const KEYCHAIN_ACCOUNT: &str = "clipboard-history";
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 500;
+/// Longer copies (whole documents, logs) are not recorded.
+const MAX_ENTRY_CHARS: usize = 100_000;
+const NONCE_LEN: usize = 12;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClipEntry {
+    pub id: u64,
+    pub text: String,
+    pub pinned: bool,
+    /// Milliseconds since the Unix epoch
+    pub at: i64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ClipboardHistory {
+    /// Most recent first
+    entries: Vec<ClipEntry>,
+    next_id: u64,
+}
+
+impl ClipboardHistory {
+    /// Add copied text. Copying something already in the history moves it
+    /// to the top. Returns whether the history changed.
+    pub fn record(&mut self, text: &str, at: i64, limit: usize) -> bool {
+        if text.trim().is_empty() || text.chars().count() > MAX_ENTRY_CHARS {
+            return false;
+        }
+        if self.entries.first().is_some_and(|entry| entry.text == text) {
+            return false;
+        }
+
+        let entry = match self.entries.iter().position(|entry| entry.text == text) {
+            Some(i) => ClipEntry { at, ..self.entries.remove(i) },
+            None => {
+                self.next_id += 1;
+                ClipEntry { id: self.next_id, text: text.to_string(), pinned: false, at }
+            }
+        };
+        self.entries.insert(0, entry);
+        self.truncate(limit);
+        true
+    }
+
+    /// Drop the oldest unpinned entries beyond `limit`. Pinned entries are
+    /// only removed explicitly.
+    pub fn truncate(&mut self, limit: usize) {
+        let mut unpinned = 0;
+        self.entries.retain(|entry| {
+            if entry.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= limit
+        });
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ClipEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn set_pinned(&mut self, id: u64, pinned: bool) -> Result<(), String> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| format!("Unknown clipboard entry: {}", id))?;
+        entry.pinned = pinned;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: u64) -> Result<(), String> {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        if self.entries.len() == before {
+            return Err(format!("Unknown clipboard entry: {}", id));
+        }
+        Ok(())
+    }
+
+    /// Remove everything except pinned entries.
+    pub fn clear(&mut self) {
+        self.entries.retain(|entry| entry.pinned);
+    }
+
+    /// Entries as the picker shows them: pinned first, then most recent.
+    pub fn list(&self) -> Vec<ClipEntry> {
+        let mut entries = self.entries.clone();
+        // Stable, so recency order is kept within each group
+        entries.sort_by_key(|entry| !entry.pinned);
+        entries
+    }
+
+    pub fn load(path: &Path, key: &[u8; 32]) -> Result<Option<Self>, String> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read clipboard history: {}", e)),
+        };
+        Self::decrypt(&data, key).map(Some)
+    }
+
+    pub fn save(&self, path: &Path, key: &[u8; 32]) -> Result<(), String> {
+        fs::write(path, self.encrypt(key)?)
+            .map_err(|e| format!("Failed to write clipboard history: {}", e))
+    }
+
+    /// A random nonce followed by the encrypted JSON.
+    fn encrypt(&self, key: &[u8; 32]) -> Result<Vec<u8>, String> {
+        let plaintext = serde_json::to_vec(self)
+            .map_err(|e| format!("Failed to serialize clipboard history: {}", e))?;
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| format!("Failed to generate nonce: {}", e))?;
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt clipboard history".to_string())?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn decrypt(data: &[u8], key: &[u8; 32]) -> Result<Self, String> {
+        if data.len() < NONCE_LEN {
+            return Err("Clipboard history file is truncated".to_string());
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt clipboard history".to_string())?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Failed to parse clipboard history: {}", e))
+    }
+}
+
+/// Read the encryption key from the OS keychain, creating it on first use.
+/// `legacy_path` is the key file of older versions, which is moved into the
+/// keychain so their history stays readable.
+pub fn load_or_create_key(legacy_path: &Path) -> Result<[u8; 32], String> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+        .map_err(|e| format!("Failed to open the keychain: {}", e))?;
+    key_from_keychain(&entry, legacy_path)
+}
+
+fn key_from_keychain(entry: &keyring::Entry, legacy_path: &Path) -> Result<[u8; 32], String> {
+    match entry.get_password() {
+        Ok(hex) => return parse_key(hex.trim()).ok_or_else(|| "Clipboard history key is corrupt".to_string()),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(format!("Failed to read clipboard history key from the keychain: {}", e)),
+    }
+
+    let key = match fs::read_to_string(legacy_path) {
+        Ok(hex) => parse_key(hex.trim()).ok_or_else(|| "Clipboard history key is corrupt".to_string())?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut key = [0u8; 32];
+            getrandom::getrandom(&mut key).map_err(|e| format!("Failed to generate key: {}", e))?;
+            key
+        }
+        Err(e) => return Err(format!("Failed to read clipboard history key: {}", e)),
+    };
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    entry.set_password(&hex)
+        .map_err(|e| format!("Failed to store clipboard history key in the keychain: {}", e))?;
+    match fs::remove_file(legacy_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            log::warn!("Failed to remove old clipboard history key: {}", e);
+        }
+        _ => {}
+    }
+    Ok(key)
+}
+
+fn parse_key(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    fn texts(history: &ClipboardHistory) -> Vec<String> {
+        history.list().into_iter().map(|entry| entry.text).collect()
+    }
+
+    #[test]
+    fn test_record_dedups_and_limits() {
+        let mut history = ClipboardHistory::default();
+        assert!(history.record("a", 1, 3));
+        assert!(history.record("b", 2, 3));
+        assert!(!history.record("b", 3, 3));
+        assert!(!history.record("   ", 4, 3));
+        assert!(history.record("a", 5, 3));
+        assert_eq!(texts(&history), ["a", "b"]);
+        assert_eq!(history.list()[0].id, 1);
+
+        history.record("c", 6, 3);
+        history.record("d", 7, 3);
+        assert_eq!(texts(&history), ["d", "c", "a"]);
+    }
+
+    #[test]
+    fn test_pinned_entries_survive_limit_and_clear() {
+        let mut history = ClipboardHistory::default();
+        history.record("keep", 1, 2);
+        let id = history.list()[0].id;
+        history.set_pinned(id, true).unwrap();
+
+        history.record("x", 2, 2);
+        history.record("y", 3, 2);
+        history.record("z", 4, 2);
+        assert_eq!(texts(&history), ["keep", "z", "y"]);
+
+        history.clear();
+        assert_eq!(texts(&history), ["keep"]);
+
+        history.remove(id).unwrap();
+        assert!(history.list().is_empty());
+        assert!(history.remove(id).is_err());
+        assert!(history.set_pinned(id, false).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let dir = TempDir::new("clipboard");
+        let path = dir.join("clipboard_history.bin");
+        let legacy = dir.join("clipboard_history.key");
+        let entry = keyring::Entry::new_with_credential(Box::new(keyring::mock::MockCredential::default()));
+        let key = key_from_keychain(&entry, &legacy).unwrap();
+        assert_eq!(key_from_keychain(&entry, &legacy).unwrap(), key);
+        assert!(!legacy.exists());
+
+        let mut history = ClipboardHistory::default();
+        history.record("secret meeting notes", 1, DEFAULT_LIMIT);
+        history.save(&path, &key).unwrap();
+
+        let raw = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("meeting"));
+
+        let loaded = ClipboardHistory::load(&path, &key).unwrap().unwrap();
+        assert_eq!(loaded.list(), history.list());
+        assert!(ClipboardHistory::load(&path, &[7u8; 32]).is_err());
+        assert!(ClipboardHistory::load(&dir.join("missing.bin"), &key).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_old_key_file_moves_to_keychain() {
+        let dir = TempDir::new("clipboard-key");
+        let legacy = dir.join("clipboard_history.key");
+        fs::write(&legacy, "ab".repeat(32)).unwrap();
+
+        let entry = keyring::Entry::new_with_credential(Box::new(keyring::mock::MockCredential::default()));
+        assert_eq!(key_from_keychain(&entry, &legacy).unwrap(), [0xab; 32]);
+        assert!(!legacy.exists());
+        assert_eq!(entry.get_password().unwrap(), "ab".repeat(32));
+    }
+}
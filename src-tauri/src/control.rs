@@ -0,0 +1,341 @@
+//! Control socket used by the CLI to drive a running instance.
+//!
+//! The protocol is one JSON request per line, answered by one JSON response
+//! line. The socket lives in the app data directory and is only accessible
+//! to the current user. Windows uses a named pipe with an owner-only ACL.
+
+use serde::{Deserialize, Serialize};
+#[cfg(any(unix, windows))]
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use crate::Website;
+
+#[cfg(unix)]
+const SOCKET_NAME: &str = "peko.sock";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Show a site and optionally navigate it. Sent by `peko open` and by
+    /// second launches of the app.
+    Activate {
+        site: Option<String>,
+        url: Option<String>,
+    },
+    List,
+    Add {
+        name: String,
+        url: String,
+        emoji: Option<String>,
+        id: Option<String>,
+    },
+    NotesAppend { text: String },
+    NotesToggle,
+    SettingsExport,
+    /// Run a command from the action registry by id
+    Run { action: String },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub result: serde_json::Value,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ControlResponse {
+    fn from_result(result: Result<serde_json::Value, String>) -> Self {
+        match result {
+            Ok(result) => ControlResponse { ok: true, result, error: None },
+            Err(error) => ControlResponse { ok: false, result: serde_json::Value::Null, error: Some(error) },
+        }
+    }
+}
+
+/// Website list as reported by `List`.
+#[derive(Serialize, Deserialize)]
+pub struct SiteList {
+    pub websites: Vec<Website>,
+    pub active_tab: String,
+}
+
+#[cfg(unix)]
+pub fn socket_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SOCKET_NAME)
+}
+
+/// Named pipes share one machine-wide namespace, so the name is derived
+/// from the app data directory, which is per user.
+#[cfg(windows)]
+pub fn pipe_name(data_dir: &Path) -> String {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    data_dir.hash(&mut hasher);
+    format!(r"\\.\pipe\peko-{:016x}", hasher.finish())
+}
+
+/// Send a request to a running instance.
+///
+/// Returns `Ok(None)` when no instance is listening.
+#[cfg(unix)]
+pub fn send(data_dir: &Path, request: &ControlRequest) -> Result<Option<ControlResponse>, String> {
+    use std::os::unix::net::UnixStream;
+
+    match UnixStream::connect(socket_path(data_dir)) {
+        Ok(stream) => exchange(stream, request).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(windows)]
+pub fn send(data_dir: &Path, request: &ControlRequest) -> Result<Option<ControlResponse>, String> {
+    use interprocess::os::windows::named_pipe::{pipe_mode, DuplexPipeStream};
+    use interprocess::ConnectWaitMode;
+
+    // Wait a little if every pipe instance is busy with another client
+    let wait = ConnectWaitMode::Timeout(std::time::Duration::from_secs(2));
+    match DuplexPipeStream::<pipe_mode::Bytes>::connect_by_path_with_wait_mode(pipe_name(data_dir), wait) {
+        Ok(stream) => exchange(stream, request).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn send(_data_dir: &Path, _request: &ControlRequest) -> Result<Option<ControlResponse>, String> {
+    Ok(None)
+}
+
+/// Write one request line and read the response line.
+#[cfg(any(unix, windows))]
+fn exchange(mut stream: impl Read + Write, request: &ControlRequest) -> Result<ControlResponse, String> {
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    serde_json::from_str(&response)
+        .map_err(|e| format!("Invalid response from Peko: {}", e))
+}
+
+/// Read one request line from a client and write back the response.
+#[cfg(any(unix, windows))]
+fn serve(app: &tauri::AppHandle, stream: impl Read + Write) {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    if let Err(e) = reader.read_line(&mut line) {
+        log::warn!("Control socket read failed: {}", e);
+        return;
+    }
+
+    let response = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => {
+            log::info!("Control request: {:?}", request);
+            ControlResponse::from_result(dispatch_on_main_thread(app, request))
+        }
+        Err(e) => ControlResponse::from_result(Err(format!("Invalid request: {}", e))),
+    };
+
+    let mut out = serde_json::to_string(&response).unwrap_or_default();
+    out.push('\n');
+    let stream = reader.get_mut();
+    let _ = stream.write_all(out.as_bytes()).and_then(|_| stream.flush());
+}
+
+/// Listen for control requests from the CLI on a background thread.
+#[cfg(unix)]
+pub fn start_server(app: &tauri::AppHandle) -> Result<(), String> {
+    use tauri::Manager;
+
+    let data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let path = socket_path(&data_dir);
+    let listener = bind_private_socket(&path)?;
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("Control socket accept failed: {}", e);
+                    continue;
+                }
+            };
+
+            // Don't let a client that never sends a line block the socket
+            let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(5)));
+            serve(&app, stream);
+        }
+    });
+
+    log::info!("Control socket listening at {}", path.display());
+    Ok(())
+}
+
+/// Bind a Unix socket that only the current user can connect to.
+#[cfg(unix)]
+pub(crate) fn bind_private_socket(path: &Path) -> Result<std::os::unix::net::UnixListener, String> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    // A socket file left behind by a crashed instance refuses connections
+    if path.exists() && UnixStream::connect(path).is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+
+    // Create the socket file without group or other access, rather than
+    // restricting it after bind when another user could already connect
+    // SAFETY: umask only swaps the process file mode mask
+    let previous = unsafe { libc::umask(0o077) };
+    let listener = UnixListener::bind(path);
+    // SAFETY: as above
+    unsafe { libc::umask(previous) };
+    let listener = listener.map_err(|e| format!("Failed to bind {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+    Ok(listener)
+}
+
+/// Listen for control requests on a named pipe that only the current user
+/// can open. Each client gets its own thread, as pipes have no read timeout.
+#[cfg(windows)]
+pub fn start_server(app: &tauri::AppHandle) -> Result<(), String> {
+    use interprocess::os::windows::named_pipe::{pipe_mode, PipeListenerOptions};
+    use interprocess::os::windows::security_descriptor::SecurityDescriptor;
+    use tauri::Manager;
+
+    let data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let name = pipe_name(&data_dir);
+
+    // Full access for the owner and SYSTEM, nothing for anyone else
+    let sddl: Vec<u16> = "D:P(A;;GA;;;SY)(A;;GA;;;OW)".encode_utf16().chain([0]).collect();
+    let descriptor = widestring::U16CStr::from_slice(&sddl)
+        .map_err(|e| e.to_string())
+        .and_then(|sddl| SecurityDescriptor::deserialize(sddl).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Failed to create pipe security descriptor: {}", e))?;
+    let mut options = PipeListenerOptions::new().path(name.as_str());
+    options.security_descriptor = Some(descriptor);
+    let listener = options.create_duplex::<pipe_mode::Bytes>()
+        .map_err(|e| format!("Failed to create {}: {}", name, e))?;
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let app = app.clone();
+                    std::thread::spawn(move || serve(&app, stream));
+                }
+                Err(e) => log::warn!("Control pipe accept failed: {}", e),
+            }
+        }
+    });
+
+    log::info!("Control pipe listening at {}", name);
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn start_server(_app: &tauri::AppHandle) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(any(unix, windows))]
+fn dispatch_on_main_thread(app: &tauri::AppHandle, request: ControlRequest) -> Result<serde_json::Value, String> {
+    run_on_main_thread(app, move |app| dispatch(app, request))
+}
+
+/// Window operations must happen on the main thread, so hop over and wait.
+#[cfg(any(unix, windows))]
+pub(crate) fn run_on_main_thread<T: Send + 'static>(
+    app: &tauri::AppHandle,
+    f: impl FnOnce(&tauri::AppHandle) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = app.clone();
+    app.run_on_main_thread(move || {
+        let _ = tx.send(f(&handle));
+    })
+    .map_err(|e| e.to_string())?;
+    rx.recv().map_err(|e| e.to_string())?
+}
+
+/// Run a request against the live app using the same code paths as the
+/// IPC commands.
+pub fn dispatch(app: &tauri::AppHandle, request: ControlRequest) -> Result<serde_json::Value, String> {
+    use tauri::Manager;
+
+    if crate::locked_out(app) {
+        return Err("Peko is locked".to_string());
+    }
+    let settings = app.state::<crate::SettingsState>().0.lock().unwrap().clone();
+
+    match request {
+        ControlRequest::Activate { site, url } => {
+            crate::open_in_site(app, site, url)?;
+            Ok(serde_json::Value::Null)
+        }
+        ControlRequest::List => serde_json::to_value(SiteList {
+            websites: settings.websites,
+            active_tab: settings.active_tab,
+        })
+        .map_err(|e| e.to_string()),
+        ControlRequest::Add { name, url, emoji, id } => {
+            let website = crate::new_website(&settings.websites, &name, &url, emoji, id)?;
+            let mut websites = settings.websites;
+            websites.push(website.clone());
+            crate::save_websites(app.clone(), websites, None)?;
+            serde_json::to_value(website).map_err(|e| e.to_string())
+        }
+        ControlRequest::NotesAppend { text } => {
+            crate::append_to_notes(app, &format!("{}\n", text.trim_end()))?;
+            Ok(serde_json::Value::Null)
+        }
+        ControlRequest::NotesToggle => {
+            let mode = crate::toggle_notes(app.clone())?;
+            serde_json::to_value(mode).map_err(|e| e.to_string())
+        }
+        ControlRequest::SettingsExport => crate::public_settings(&settings),
+        ControlRequest::Run { action } => {
+            crate::actions::run(app, &action)?;
+            Ok(serde_json::Value::Null)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_wire_format() {
+        let request = ControlRequest::Activate { site: Some("gemini".to_string()), url: None };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(json, r#"{"command":"activate","site":"gemini","url":null}"#);
+
+        let parsed: ControlRequest = serde_json::from_str(r#"{"command":"notes_append","text":"hi"}"#).unwrap();
+        assert_eq!(parsed, ControlRequest::NotesAppend { text: "hi".to_string() });
+
+        assert!(serde_json::from_str::<ControlRequest>(r#"{"command":"eval"}"#).is_err());
+    }
+
+    #[test]
+    fn test_response_from_result() {
+        let ok = ControlResponse::from_result(Ok(serde_json::json!([1])));
+        assert!(ok.ok && ok.error.is_none());
+
+        let err = ControlResponse::from_result(Err("nope".to_string()));
+        assert!(!err.ok);
+        assert_eq!(err.error.as_deref(), Some("nope"));
+    }
+}
@@ -0,0 +1,167 @@
+//! `peko://` deep links.
+//!
+//! Links are parsed into the same `ControlRequest`s the CLI sends, so they go
+//! through the same checks. Only the actions below are accepted; anything
+//! else is rejected instead of being guessed at, since any web page can
+//! link to `peko://`. For the same reason links can't write anything, such
+//! as appending to notes; that takes the CLI or the automation API.
+//!
+//! - `peko://open/<site>`
+//! - `peko://open?site=<site>&url=<url>` (both optional; the URL must be on
+//!   the site's host)
+//! - `peko://notes/toggle`
+
+use crate::control::ControlRequest;
+use crate::instance;
+
+pub const SCHEME: &str = "peko";
+
+/// Whether a command-line argument is a link the OS handed to us.
+pub fn is_deep_link(arg: &str) -> bool {
+    let prefix = SCHEME.len() + "://".len();
+    arg.len() > prefix && arg.get(..prefix).is_some_and(|p| p.eq_ignore_ascii_case("peko://"))
+}
+
+pub fn parse(link: &str) -> Result<ControlRequest, String> {
+    let url = url::Url::parse(link).map_err(|e| format!("Invalid link {}: {}", link, e))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("Not a {}:// link: {}", SCHEME, link));
+    }
+
+    let action = url.host_str().unwrap_or_default();
+    let path = url.path().trim_matches('/');
+    let mut params = Params::from(&url);
+
+    let request = match (action, path) {
+        ("open", path) => {
+            let site = match (path, params.take("site")?) {
+                ("", site) => site,
+                (path, None) if !path.contains('/') => Some(path.to_string()),
+                _ => return Err("Give the site either in the path or as ?site=, not both".to_string()),
+            };
+            let url = params.take("url")?;
+            if let Some(url) = &url {
+                instance::parse_web_url(url)?;
+            }
+            if site.is_none() && url.is_none() {
+                return Err("peko://open needs a site or a url".to_string());
+            }
+            ControlRequest::Activate { site, url }
+        }
+        ("notes", "toggle") => ControlRequest::NotesToggle,
+        _ => return Err(format!("Unsupported link: {}", link)),
+    };
+
+    params.finish()?;
+    Ok(request)
+}
+
+/// Query parameters, each allowed at most once and all of them consumed.
+struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn from(url: &url::Url) -> Self {
+        Params(url.query_pairs().into_owned().collect())
+    }
+
+    fn take(&mut self, name: &str) -> Result<Option<String>, String> {
+        let mut values = Vec::new();
+        self.0.retain(|(key, value)| {
+            if key == name {
+                values.push(value.clone());
+                false
+            } else {
+                true
+            }
+        });
+        match values.len() {
+            0 => Ok(None),
+            1 => Ok(values.pop()),
+            _ => Err(format!("Parameter {} given more than once", name)),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self.0.first() {
+            Some((key, _)) => Err(format!("Unknown parameter: {}", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activate(site: Option<&str>, url: Option<&str>) -> ControlRequest {
+        ControlRequest::Activate { site: site.map(String::from), url: url.map(String::from) }
+    }
+
+    #[test]
+    fn test_parse_open_links() {
+        assert_eq!(parse("peko://open/gemini"), Ok(activate(Some("gemini"), None)));
+        assert_eq!(parse("peko://open/gemini/"), Ok(activate(Some("gemini"), None)));
+        assert_eq!(
+            parse("peko://open?url=https%3A%2F%2Fnotebooklm.google.com%2F&site=notebooklm"),
+            Ok(activate(Some("notebooklm"), Some("https://notebooklm.google.com/")))
+        );
+        assert_eq!(
+            parse("peko://open?url=https://example.com/a?b=c"),
+            Ok(activate(None, Some("https://example.com/a?b=c")))
+        );
+    }
+
+    #[test]
+    fn test_parse_notes_links() {
+        assert_eq!(parse("peko://notes/toggle"), Ok(ControlRequest::NotesToggle));
+        // Any page could write into the notes
+        assert!(parse("peko://notes/append?text=Hello%20world").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_everything_else() {
+        for link in [
+            "https://open/gemini",
+            "peko://open",
+            "peko://open/gemini/extra",
+            "peko://open/gemini?site=chatgpt",
+            "peko://open?url=javascript:alert(1)",
+            "peko://open?url=file:///etc/passwd",
+            "peko://open/gemini?eval=1",
+            "peko://open?site=a&site=b",
+            "peko://notes/toggle?mode=window",
+            "peko://settings/export",
+            "peko://add?name=x&url=https://evil.example",
+        ] {
+            assert!(parse(link).is_err(), "{} should be rejected", link);
+        }
+    }
+
+    #[test]
+    fn test_open_links_stay_on_their_site() {
+        let websites = crate::AppSettings::default().websites;
+        let target = |link: &str| match parse(link)? {
+            ControlRequest::Activate { site, url } => {
+                let url = url.as_deref().map(instance::parse_web_url).transpose()?;
+                instance::target_site(&websites, site.as_deref(), url.as_ref())
+            }
+            request => Err(format!("Not an open link: {:?}", request)),
+        };
+
+        assert_eq!(target("peko://open?site=gemini&url=https://gemini.google.com/app"), Ok(Some("gemini".to_string())));
+        assert_eq!(target("peko://open?url=https://gemini.google.com/app"), Ok(Some("gemini".to_string())));
+        // A URL on another host, with or without a site
+        assert!(target("peko://open?site=gemini&url=https://evil.example").is_err());
+        assert!(target("peko://open/gemini?url=https://evil.example").is_err());
+        assert!(target("peko://open?url=https://evil.example").is_err());
+    }
+
+    #[test]
+    fn test_is_deep_link() {
+        assert!(is_deep_link("peko://open/gemini"));
+        assert!(is_deep_link("PEKO://open/gemini"));
+        assert!(!is_deep_link("peko://"));
+        assert!(!is_deep_link("https://gemini.google.com"));
+        assert!(!is_deep_link("open"));
+    }
+}
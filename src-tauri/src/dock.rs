@@ -0,0 +1,93 @@
+//! Geometry for docking the notes sidebar next to a site window.
+//!
+//! All values are physical pixels; the sidebar size is configured in logical
+//! pixels and scaled by the site window's scale factor so it looks the same
+//! on every monitor.
+
+use serde::{Deserialize, Serialize};
+
+/// Which edge of the site window the notes sidebar attaches to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DockPosition {
+    Left,
+    Bottom,
+    // Unknown values (e.g. from a newer version) fall back to the default.
+    // `serde(other)` must be on the last variant.
+    #[default]
+    #[serde(other)]
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Place the sidebar flush against `main` on the given edge.
+///
+/// `size` is the sidebar's width when docked left/right, or its height when
+/// docked at the bottom.
+pub fn sidebar_rect(main: Rect, scale_factor: f64, size: f64, position: DockPosition) -> Rect {
+    let size = (size * scale_factor).round().max(1.0) as u32;
+    match position {
+        DockPosition::Left => Rect {
+            x: main.x - size as i32,
+            y: main.y,
+            width: size,
+            height: main.height,
+        },
+        DockPosition::Right => Rect {
+            x: main.x + main.width as i32,
+            y: main.y,
+            width: size,
+            height: main.height,
+        },
+        DockPosition::Bottom => Rect {
+            x: main.x,
+            y: main.y + main.height as i32,
+            width: main.width,
+            height: size,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: Rect = Rect { x: 100, y: 50, width: 1200, height: 800 };
+
+    #[test]
+    fn test_sidebar_rect_docks_right() {
+        let rect = sidebar_rect(MAIN, 1.0, 350.0, DockPosition::Right);
+        assert_eq!(rect, Rect { x: 1300, y: 50, width: 350, height: 800 });
+    }
+
+    #[test]
+    fn test_sidebar_rect_docks_left_and_bottom() {
+        let rect = sidebar_rect(MAIN, 1.0, 350.0, DockPosition::Left);
+        assert_eq!(rect, Rect { x: -250, y: 50, width: 350, height: 800 });
+
+        let rect = sidebar_rect(MAIN, 1.0, 300.0, DockPosition::Bottom);
+        assert_eq!(rect, Rect { x: 100, y: 850, width: 1200, height: 300 });
+    }
+
+    #[test]
+    fn test_sidebar_rect_scales_size() {
+        let main = Rect { x: -2400, y: 0, width: 2400, height: 1600 };
+        let rect = sidebar_rect(main, 2.0, 350.0, DockPosition::Right);
+        assert_eq!(rect, Rect { x: 0, y: 0, width: 700, height: 1600 });
+    }
+
+    #[test]
+    fn test_dock_position_tolerates_unknown_values() {
+        let position: DockPosition = serde_json::from_str("\"top\"").unwrap();
+        assert_eq!(position, DockPosition::Right);
+        let position: DockPosition = serde_json::from_str("\"bottom\"").unwrap();
+        assert_eq!(position, DockPosition::Bottom);
+    }
+}
@@ -0,0 +1,923 @@
+//! Export notes documents to standalone HTML, PDF or a zip of Markdown files.
+//!
+//! Rendering happens entirely in Rust so exports look the same regardless of
+//! which window triggered them and can be tested without a webview. PDFs use
+//! the standard fonts, plus installed fonts (subset and embedded) for text
+//! those can't show, like CJK, Cyrillic or Greek.
+
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Cursor, Write};
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Html,
+    Pdf,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Markdown => "zip",
+        }
+    }
+}
+
+pub struct ExportDocument<'a> {
+    pub id: &'a str,
+    pub title: &'a str,
+    pub content: &'a str,
+}
+
+fn markdown_options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render Markdown to an HTML fragment. Raw HTML in the notes is escaped
+/// rather than passed through, since clipped content comes from web pages.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, markdown_options()).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    html
+}
+
+const HTML_STYLE: &str = r#"
+body { max-width: 760px; margin: 40px auto; padding: 0 20px; font: 15px/1.6 -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; color: #1a1a2e; }
+h1, h2, h3 { line-height: 1.25; }
+code { background: #f0f0f5; padding: 2px 5px; border-radius: 4px; font-family: 'SF Mono', Monaco, monospace; font-size: 0.9em; }
+pre { background: #f0f0f5; padding: 12px; border-radius: 8px; overflow-x: auto; }
+pre code { background: transparent; padding: 0; }
+blockquote { border-left: 3px solid #e94560; margin-left: 0; padding-left: 12px; color: #555; }
+a { color: #e94560; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; }
+section + section { border-top: 1px solid #ddd; margin-top: 40px; }
+footer { margin-top: 40px; color: #888; font-size: 12px; }
+"#;
+
+/// Render documents as a single self-contained HTML page.
+pub fn render_html(docs: &[ExportDocument], exported_at: &str) -> String {
+    let title = match docs {
+        [doc] => doc.title,
+        _ => "Peko Notes",
+    };
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape_html(title),
+        HTML_STYLE
+    );
+    for doc in docs {
+        html.push_str(&format!("<section id=\"{}\">\n", escape_html(doc.id)));
+        if docs.len() > 1 {
+            html.push_str(&format!("<h1>{}</h1>\n", escape_html(doc.title)));
+        }
+        html.push_str(&markdown_to_html(doc.content));
+        html.push_str("</section>\n");
+    }
+    html.push_str(&format!(
+        "<footer>Exported from Peko on {}</footer>\n</body>\n</html>\n",
+        escape_html(exported_at)
+    ));
+    html
+}
+
+/// Quote a value for YAML front matter.
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+/// Make a document id safe to use as a file name inside the archive.
+fn file_stem(id: &str) -> String {
+    let stem: String = id
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if stem.is_empty() { "notes".to_string() } else { stem }
+}
+
+pub fn markdown_with_front_matter(doc: &ExportDocument, exported_at: &str) -> String {
+    format!(
+        "---\ntitle: {}\nid: {}\nexported: {}\n---\n\n{}\n",
+        yaml_string(doc.title),
+        yaml_string(doc.id),
+        yaml_string(exported_at),
+        doc.content.trim_end()
+    )
+}
+
+/// Zip every document as `<id>.md` with YAML front matter.
+pub fn render_markdown_bundle(docs: &[ExportDocument], exported_at: &str) -> Result<Vec<u8>, String> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for doc in docs {
+        zip.start_file(format!("{}.md", file_stem(doc.id)), options)
+            .map_err(|e| format!("Failed to add {} to archive: {}", doc.id, e))?;
+        zip.write_all(markdown_with_front_matter(doc, exported_at).as_bytes())
+            .map_err(|e| format!("Failed to write {} to archive: {}", doc.id, e))?;
+    }
+
+    let cursor = zip.finish().map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+// ===== PDF =====
+
+const PAGE_WIDTH: f32 = 595.0; // A4 in points
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+
+    /// Rough average glyph width as a fraction of the font size, used for wrapping.
+    fn char_width(self) -> f32 {
+        match self {
+            Font::Regular => 0.5,
+            Font::Bold => 0.55,
+            Font::Mono => 0.6,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Block {
+    font: Font,
+    size: f32,
+    indent: f32,
+    text: String,
+}
+
+/// Flatten Markdown into styled text blocks. Inline emphasis is dropped;
+/// headings, lists, quotes and code blocks keep their structure.
+fn markdown_blocks(markdown: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut quote_depth = 0;
+
+    let base_indent = |lists: usize, quotes: usize| (lists + quotes) as f32 * 14.0;
+
+    for event in Parser::new_ext(markdown, markdown_options()) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                let size = match level {
+                    HeadingLevel::H1 => 20.0,
+                    HeadingLevel::H2 => 16.0,
+                    HeadingLevel::H3 => 14.0,
+                    _ => 12.0,
+                };
+                current = Some(Block { font: Font::Bold, size, indent: 0.0, text: String::new() });
+            }
+            // Paragraphs inside list items continue the item's block
+            Event::Start(Tag::Paragraph) if current.is_none() => {
+                current = Some(Block {
+                    font: Font::Regular,
+                    size: 11.0,
+                    indent: base_indent(lists.len(), quote_depth),
+                    text: String::new(),
+                });
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                current = Some(Block {
+                    font: Font::Mono,
+                    size: 9.5,
+                    indent: base_indent(lists.len(), quote_depth) + 8.0,
+                    text: String::new(),
+                });
+            }
+            Event::Start(Tag::BlockQuote(_)) => quote_depth += 1,
+            Event::End(TagEnd::BlockQuote(_)) => quote_depth -= 1,
+            Event::Start(Tag::List(start)) => {
+                if let Some(block) = current.take() {
+                    blocks.push(block);
+                }
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                let marker = match lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "\u{2022} ".to_string(),
+                };
+                current = Some(Block {
+                    font: Font::Regular,
+                    size: 11.0,
+                    indent: base_indent(lists.len() - 1, quote_depth),
+                    text: marker,
+                });
+            }
+            Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::CodeBlock)
+            | Event::End(TagEnd::Item) => {
+                if let Some(block) = current.take() {
+                    if !block.text.trim().is_empty() {
+                        blocks.push(block);
+                    }
+                }
+            }
+            Event::Text(text) | Event::Code(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push_str(&text);
+                }
+            }
+            Event::TaskListMarker(done) => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push_str(if done { "[x] " } else { "[ ] " });
+                }
+            }
+            Event::SoftBreak => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push(' ');
+                }
+            }
+            Event::HardBreak => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push('\n');
+                }
+            }
+            Event::Rule => blocks.push(Block {
+                font: Font::Regular,
+                size: 11.0,
+                indent: 0.0,
+                text: "\u{2014}".repeat(20),
+            }),
+            _ => {}
+        }
+    }
+    if let Some(block) = current.take() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// Columns `c` takes up: 2 for CJK and other wide characters, which are
+/// about a full em wide, 1 for the rest.
+fn columns(c: char) -> usize {
+    c.width().unwrap_or(0).max(1)
+}
+
+fn text_columns(text: &str) -> usize {
+    text.chars().map(columns).sum()
+}
+
+/// Greedy word wrap to fit `max_cols` per line, preserving explicit newlines.
+/// Words wider than a line, such as CJK text without spaces, are split
+/// between characters.
+fn wrap(text: &str, max_cols: usize) -> Vec<String> {
+    let max_cols = max_cols.max(2);
+    let mut lines = Vec::new();
+    for raw in text.trim_end_matches('\n').split('\n') {
+        let mut line = String::new();
+        let mut cols = 0;
+        for word in raw.split(' ') {
+            if cols > 0 && cols + 1 + text_columns(word) > max_cols {
+                lines.push(std::mem::take(&mut line));
+                cols = 0;
+            }
+            if cols > 0 {
+                line.push(' ');
+                cols += 1;
+            }
+            for c in word.chars() {
+                if cols > 0 && cols + columns(c) > max_cols {
+                    lines.push(std::mem::take(&mut line));
+                    cols = 0;
+                }
+                line.push(c);
+                cols += columns(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Split code into fixed-width chunks, keeping indentation intact.
+fn wrap_code(text: &str, max_cols: usize) -> Vec<String> {
+    let max_cols = max_cols.max(2);
+    let mut lines = Vec::new();
+    for raw in text.trim_end_matches('\n').split('\n') {
+        let mut line = String::new();
+        let mut cols = 0;
+        for c in raw.chars() {
+            if cols + columns(c) > max_cols {
+                lines.push(std::mem::take(&mut line));
+                cols = 0;
+            }
+            line.push(c);
+            cols += columns(c);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Control characters, which are left out of the PDF.
+fn is_control(c: char) -> bool {
+    (c as u32) < 0x20 && c != '\t'
+}
+
+/// The character's code in WinAnsiEncoding, the encoding of the standard
+/// fonts, if it has one.
+fn win_ansi(c: char) -> Option<u32> {
+    match c {
+        '\u{2022}' => Some(0x95),
+        '\u{2013}' => Some(0x96),
+        '\u{2014}' => Some(0x97),
+        '\u{2018}' => Some(0x91),
+        '\u{2019}' => Some(0x92),
+        '\u{201C}' => Some(0x93),
+        '\u{201D}' => Some(0x94),
+        '\u{2026}' => Some(0x85),
+        '\u{20AC}' => Some(0x80),
+        '\t' => Some(0x20),
+        c if (0x20..=0x7E).contains(&(c as u32)) || (0xA0..=0xFF).contains(&(c as u32)) => Some(c as u32),
+        _ => None,
+    }
+}
+
+/// Encode text for a PDF string literal using WinAnsiEncoding.
+/// Characters outside that encoding are replaced with '?'.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for c in text.chars() {
+        if is_control(c) {
+            continue;
+        }
+        let code = win_ansi(c).unwrap_or('?' as u32);
+        match code {
+            0x28 => out.push_str("\\("),
+            0x29 => out.push_str("\\)"),
+            0x5C => out.push_str("\\\\"),
+            0x20..=0x7E => out.push(code as u8 as char),
+            _ => out.push_str(&format!("\\{:03o}", code)),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Most installed fonts embedded in one export.
+const MAX_FALLBACK_FONTS: usize = 8;
+
+/// An installed font, subset to the characters it draws, embedded for text
+/// outside WinAnsiEncoding. Glyphs are addressed by glyph id (Identity-H
+/// encoding), and the subset keeps the original ids.
+pub struct FallbackFont {
+    name: String,
+    cff: bool,
+    program: Vec<u8>,
+    ascent: i32,
+    descent: i32,
+    cap_height: i32,
+    bbox: [i32; 4],
+    /// Glyph id and advance width in thousandths of an em, per character.
+    glyphs: BTreeMap<char, (u16, u32)>,
+}
+
+impl FallbackFont {
+    /// Subset the font in `data` to whichever of `chars` it has glyphs for.
+    /// None if it has none of them or its outlines can't be embedded.
+    pub fn new(data: &[u8], index: u32, name: &str, chars: &BTreeSet<char>) -> Option<Self> {
+        let face = ttf_parser::Face::parse(data, index).ok()?;
+        let tables = face.tables();
+        let cff = match (tables.glyf.is_some(), tables.cff.is_some()) {
+            (true, _) => false,
+            (false, true) => true,
+            // Variable CFF2 and bitmap-only fonts
+            _ => return None,
+        };
+        let scale = |units: i32| units * 1000 / i32::from(face.units_per_em().max(1));
+        let glyphs: BTreeMap<char, (u16, u32)> = chars
+            .iter()
+            .filter_map(|&c| {
+                let glyph = face.glyph_index(c)?;
+                let advance = face.glyph_hor_advance(glyph).unwrap_or(0);
+                Some((c, (glyph.0, scale(i32::from(advance)) as u32)))
+            })
+            .collect();
+        if glyphs.is_empty() {
+            return None;
+        }
+
+        let mut ids: Vec<u16> = glyphs.values().map(|(glyph, _)| *glyph).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let program = subsetter::subset(data, index, subsetter::Profile::pdf(&ids)).ok()?;
+
+        // Subsets get a six-letter tag in front of the font name
+        let hash = ids.iter().fold(5381u32, |h, id| h.wrapping_mul(33) ^ u32::from(*id));
+        let tag: String = (0..6).map(|i| (b'A' + (hash >> (i * 5)) as u8 % 26) as char).collect();
+        let name: String = name.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+
+        let bbox = face.global_bounding_box();
+        let ascent = scale(i32::from(face.ascender()));
+        Some(FallbackFont {
+            name: format!("{}+{}", tag, if name.is_empty() { "Font" } else { &name }),
+            cff,
+            program,
+            ascent,
+            descent: scale(i32::from(face.descender())),
+            cap_height: face.capital_height().map(|h| scale(i32::from(h))).unwrap_or(ascent),
+            bbox: [
+                scale(i32::from(bbox.x_min)),
+                scale(i32::from(bbox.y_min)),
+                scale(i32::from(bbox.x_max)),
+                scale(i32::from(bbox.y_max)),
+            ],
+            glyphs,
+        })
+    }
+
+    /// The Type0 font, CIDFont, descriptor, font file and ToUnicode CMap,
+    /// numbered from `id`.
+    fn objects(&self, id: usize) -> Vec<Vec<u8>> {
+        let widths: Vec<String> = self
+            .glyphs
+            .values()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|(glyph, width)| format!("{} [{}]", glyph, width))
+            .collect();
+        let (subtype, gid_map, file_key, file_subtype) = if self.cff {
+            ("CIDFontType0", "", "FontFile3", " /Subtype /OpenType")
+        } else {
+            ("CIDFontType2", " /CIDToGIDMap /Identity", "FontFile2", "")
+        };
+
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        let program = encoder.write_all(&self.program).and_then(|_| encoder.finish()).unwrap_or_default();
+        let mut file = format!(
+            "<< /Length {} /Length1 {} /Filter /FlateDecode{} >>\nstream\n",
+            program.len(),
+            self.program.len(),
+            file_subtype
+        )
+        .into_bytes();
+        file.extend_from_slice(&program);
+        file.extend_from_slice(b"\nendstream");
+
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+             1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+        );
+        let mut seen = BTreeSet::new();
+        let mappings: Vec<(u16, char)> =
+            self.glyphs.iter().filter(|(_, (glyph, _))| seen.insert(*glyph)).map(|(c, (glyph, _))| (*glyph, *c)).collect();
+        for chunk in mappings.chunks(100) {
+            cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+            for (glyph, c) in chunk {
+                let units: String = c.encode_utf16(&mut [0; 2]).iter().map(|u| format!("{:04X}", u)).collect();
+                cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, units));
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+
+        vec![
+            format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+                self.name,
+                id + 1,
+                id + 4
+            )
+            .into_bytes(),
+            format!(
+                "<< /Type /Font /Subtype /{} /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R /DW 1000 /W [{}]{} >>",
+                subtype,
+                self.name,
+                id + 2,
+                widths.join(" "),
+                gid_map
+            )
+            .into_bytes(),
+            format!(
+                "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /{} {} 0 R >>",
+                self.name, self.bbox[0], self.bbox[1], self.bbox[2], self.bbox[3], self.ascent, self.descent, self.cap_height, file_key, id + 3
+            )
+            .into_bytes(),
+            file,
+            format!("<< /Length {} >>\nstream\n{}endstream", cmap.len(), cmap).into_bytes(),
+        ]
+    }
+}
+
+/// Characters in the documents that the standard fonts can't show.
+fn missing_chars(docs: &[ExportDocument]) -> BTreeSet<char> {
+    docs.iter()
+        .flat_map(|doc| doc.title.chars().chain(doc.content.chars()))
+        .filter(|c| !is_control(*c) && win_ansi(*c).is_none())
+        .collect()
+}
+
+/// Pick installed fonts that cover the documents' characters outside
+/// WinAnsiEncoding, preferring regular weight faces that cover the most.
+pub fn fallback_fonts(docs: &[ExportDocument]) -> Vec<FallbackFont> {
+    let mut missing = missing_chars(docs);
+    if missing.is_empty() {
+        return Vec::new();
+    }
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+    // Parse each face once for which of the characters it has
+    let mut faces: Vec<(fontdb::ID, String, BTreeSet<char>)> = {
+        let mut infos: Vec<&fontdb::FaceInfo> = db.faces().collect();
+        infos.sort_by_key(|face| (face.style != fontdb::Style::Normal, face.weight.0.abs_diff(400), face.monospaced));
+        infos
+            .into_iter()
+            .filter_map(|info| {
+                let covered: BTreeSet<char> = db
+                    .with_face_data(info.id, |data, index| {
+                        let face = ttf_parser::Face::parse(data, index).ok()?;
+                        Some(missing.iter().copied().filter(|c| face.glyph_index(*c).is_some()).collect())
+                    })
+                    .flatten()?;
+                (!covered.is_empty()).then(|| (info.id, info.post_script_name.clone(), covered))
+            })
+            .collect()
+    };
+
+    let mut fonts = Vec::new();
+    while !missing.is_empty() && fonts.len() < MAX_FALLBACK_FONTS {
+        let mut best: Option<(usize, usize)> = None;
+        for (i, (_, _, covers)) in faces.iter().enumerate() {
+            let covered = covers.intersection(&missing).count();
+            if covered > best.map_or(0, |(_, most)| most) {
+                best = Some((i, covered));
+            }
+        }
+        let Some((i, _)) = best else { break };
+        let (id, name, _) = faces.remove(i);
+        let Some(font) = db.with_face_data(id, |data, index| FallbackFont::new(data, index, &name, &missing)).flatten()
+        else {
+            continue;
+        };
+        missing.retain(|c| !font.glyphs.contains_key(c));
+        fonts.push(font);
+    }
+    fonts
+}
+
+/// Text operators drawing one line at (`x`, `y`). Lines the standard font
+/// can show are a single string; others switch to the fallback fonts
+/// (resources F4 and up) for the characters it lacks.
+fn show_line(block: &Block, x: f32, y: f32, line: &str, fonts: &[FallbackFont]) -> String {
+    let base = block.font.resource();
+    let font_for = |c: char| match win_ansi(c) {
+        Some(_) => None,
+        None => fonts.iter().position(|font| font.glyphs.contains_key(&c)),
+    };
+    let chars: Vec<char> = line.chars().filter(|c| !is_control(*c)).collect();
+    if chars.iter().all(|c| font_for(*c).is_none()) {
+        return format!("BT /{} {} Tf {:.1} {:.1} Td {} Tj ET\n", base, block.size, x, y, pdf_string(line));
+    }
+
+    let mut out = format!("BT {:.1} {:.1} Td", x, y);
+    let mut rest = chars.as_slice();
+    while let Some(first) = rest.first() {
+        let font = font_for(*first);
+        let len = rest.iter().position(|c| font_for(*c) != font).unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(len);
+        rest = tail;
+        match font {
+            None => {
+                let text: String = run.iter().collect();
+                out.push_str(&format!(" /{} {} Tf {} Tj", base, block.size, pdf_string(&text)));
+            }
+            Some(i) => {
+                let glyphs: String = run.iter().map(|c| format!("{:04X}", fonts[i].glyphs[c].0)).collect();
+                // Installed fonts are regular weight, so bold is drawn with an outline
+                let (bold, regular) = if block.font == Font::Bold {
+                    (format!(" 2 Tr {:.2} w", block.size * 0.03), " 0 Tr")
+                } else {
+                    (String::new(), "")
+                };
+                out.push_str(&format!(" /F{} {} Tf{} <{}> Tj{}", i + 4, block.size, bold, glyphs, regular));
+            }
+        }
+    }
+    out.push_str(" ET\n");
+    out
+}
+
+/// Lay out documents as a simple text PDF with the standard base-14 fonts,
+/// plus `fonts` from [`fallback_fonts`] for characters those lack.
+pub fn render_pdf(docs: &[ExportDocument], fonts: &[FallbackFont]) -> Vec<u8> {
+    let mut pages: Vec<String> = Vec::new();
+    let mut page = String::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    for (i, doc) in docs.iter().enumerate() {
+        let mut blocks = markdown_blocks(doc.content);
+        if docs.len() > 1 {
+            blocks.insert(0, Block { font: Font::Bold, size: 22.0, indent: 0.0, text: doc.title.to_string() });
+            // Each document starts on a fresh page
+            if i > 0 && !page.is_empty() {
+                pages.push(std::mem::take(&mut page));
+                y = PAGE_HEIGHT - MARGIN;
+            }
+        }
+
+        for block in blocks {
+            let leading = block.size * 1.4;
+            let width = PAGE_WIDTH - 2.0 * MARGIN - block.indent;
+            let max_chars = (width / (block.size * block.font.char_width())) as usize;
+
+            // Space above headings
+            if block.font == Font::Bold && y < PAGE_HEIGHT - MARGIN {
+                y -= block.size * 0.6;
+            }
+
+            let lines = if block.font == Font::Mono {
+                wrap_code(&block.text, max_chars)
+            } else {
+                wrap(&block.text, max_chars)
+            };
+            for line in lines {
+                if y - leading < MARGIN {
+                    pages.push(std::mem::take(&mut page));
+                    y = PAGE_HEIGHT - MARGIN;
+                }
+                y -= leading;
+                page.push_str(&show_line(&block, MARGIN + block.indent, y, &line, fonts));
+            }
+            y -= block.size * 0.5;
+        }
+    }
+    if !page.is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+
+    // Objects: 1 catalog, 2 page tree, 3-5 standard fonts, five per fallback
+    // font, then a page + content pair per page
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        Vec::new(), // page tree, filled in below
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+    let mut font_resources = "/F1 3 0 R /F2 4 0 R /F3 5 0 R".to_string();
+    for (i, font) in fonts.iter().enumerate() {
+        let id = objects.len() + 1;
+        font_resources.push_str(&format!(" /F{} {} 0 R", i + 4, id));
+        objects.extend(font.objects(id));
+    }
+    let mut kids = Vec::new();
+    for content in &pages {
+        let page_id = objects.len() + 1;
+        kids.push(format!("{} 0 R", page_id));
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {} >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                font_resources,
+                page_id + 1
+            )
+            .into_bytes(),
+        );
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content).into_bytes());
+    }
+    objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes();
+
+    // OpenType font files need PDF 1.6
+    let version = if fonts.iter().any(|font| font.cff) { "1.6" } else { "1.4" };
+    let mut pdf = format!("%PDF-{}\n", version).into_bytes();
+    pdf.extend_from_slice(b"%\xE2\xE3\xCF\xD3\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn doc(content: &str) -> ExportDocument<'_> {
+        ExportDocument { id: "notes", title: "Notes", content }
+    }
+
+    #[test]
+    fn test_markdown_to_html_escapes_raw_html() {
+        let html = markdown_to_html("# Title\n\n**bold** <script>alert(1)</script>");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_render_html_is_standalone() {
+        let html = render_html(&[doc("hello")], "2026-01-01 10:00");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Notes</title>"));
+        assert!(html.contains("<p>hello</p>"));
+        assert!(html.contains("Exported from Peko on 2026-01-01 10:00"));
+    }
+
+    #[test]
+    fn test_markdown_bundle_has_front_matter() {
+        let docs = [
+            doc("first"),
+            ExportDocument { id: "work/../x", title: "Work \"notes\"", content: "second" },
+        ];
+        let bytes = render_markdown_bundle(&docs, "2026-01-01").unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 2);
+
+        let mut content = String::new();
+        archive.by_name("work____x.md").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(
+            content,
+            "---\ntitle: \"Work \\\"notes\\\"\"\nid: \"work/../x\"\nexported: \"2026-01-01\"\n---\n\nsecond\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_blocks_structure() {
+        let blocks = markdown_blocks("# Head\n\ntext\nmore\n\n- one\n- two\n\n1. first\n\n```\ncode\n```");
+        let texts: Vec<&str> = blocks.iter().map(|b| b.text.as_str()).collect();
+        assert_eq!(texts, ["Head", "text more", "\u{2022} one", "\u{2022} two", "1. first", "code\n"]);
+        assert_eq!(blocks[0].font, Font::Bold);
+        assert_eq!(blocks[5].font, Font::Mono);
+    }
+
+    #[test]
+    fn test_wrap_splits_long_lines() {
+        assert_eq!(wrap("aaa bbb ccc", 7), ["aaa bbb", "ccc"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("a\nb", 10), ["a", "b"]);
+        assert_eq!(wrap_code("  if x {\n\n    y\n", 6), ["  if x", " {", "", "    y"]);
+        // Wide characters take two columns and split without spaces
+        assert_eq!(wrap("中文中文 ab", 5), ["中文", "中文", "ab"]);
+    }
+
+    #[test]
+    fn test_pdf_string_escapes() {
+        assert_eq!(pdf_string("a(b)\\"), "(a\\(b\\)\\\\)");
+        assert_eq!(pdf_string("\u{2022} é 中"), "(\\225 \\351 ?)");
+    }
+
+    #[test]
+    fn test_render_pdf_structure() {
+        let long = "word ".repeat(2000);
+        let pdf = render_pdf(&[doc(&long)], &[]);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+        // Long content spills onto several pages
+        assert!(text.matches("/Type /Page ").count() > 1);
+
+        // xref offset points at the xref table
+        let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref"));
+    }
+
+    /// A TrueType font with empty glyphs for 中 (id 1) and 文 (id 2).
+    fn cjk_font() -> Vec<u8> {
+        fn u16s(values: &[u16]) -> Vec<u8> {
+            values.iter().flat_map(|v| v.to_be_bytes()).collect()
+        }
+        let cmap = [
+            u16s(&[0, 1, 3, 1, 0, 12]),
+            // Format 4 with segments for 中, 文 and the final 0xFFFF
+            u16s(&[4, 40, 0, 6, 4, 1, 2]),
+            u16s(&[0x4E2D, 0x6587, 0xFFFF, 0]),
+            u16s(&[0x4E2D, 0x6587, 0xFFFF]),
+            u16s(&[1u16.wrapping_sub(0x4E2D), 2u16.wrapping_sub(0x6587), 1]),
+            u16s(&[0, 0, 0]),
+        ]
+        .concat();
+        let mut head = u16s(&[1, 0, 1, 0, 0, 0, 0x5F0F, 0x3CF5, 0, 1000]);
+        head.resize(36, 0);
+        head.extend(u16s(&[0, 0xFF88, 1000, 880, 0, 8, 2, 0, 0]));
+        let mut hhea = u16s(&[1, 0, 880, 0xFF88]);
+        hhea.resize(34, 0);
+        hhea.extend(u16s(&[3]));
+        let mut maxp = u16s(&[1, 0, 3]);
+        maxp.resize(32, 0);
+        let tables: [(&[u8; 4], Vec<u8>); 7] = [
+            (b"cmap", cmap),
+            (b"glyf", Vec::new()),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", u16s(&[1000, 0, 1000, 0, 1000, 0])),
+            (b"loca", u16s(&[0, 0, 0, 0])),
+            (b"maxp", maxp),
+        ];
+
+        let mut font = u16s(&[1, 0, tables.len() as u16, 64, 2, 48]);
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, data) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend([0; 4]);
+            font.extend((offset as u32).to_be_bytes());
+            font.extend((data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for (_, data) in &tables {
+            font.extend(data);
+            font.resize(font.len().next_multiple_of(4), 0);
+        }
+        font
+    }
+
+    #[test]
+    fn test_render_pdf_embeds_font_for_cjk() {
+        let docs = [doc("# 中文\n\nnotes 中文 \u{1F600}")];
+        let missing = missing_chars(&docs);
+        assert_eq!(missing, BTreeSet::from(['中', '文', '\u{1F600}']));
+
+        let font = FallbackFont::new(&cjk_font(), 0, "Test CJK", &missing).unwrap();
+        assert_eq!(font.glyphs.len(), 2);
+        let pdf = render_pdf(&docs, &[font]);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/Subtype /Type0 /BaseFont /"));
+        assert!(text.contains("+TestCJK /Encoding /Identity-H"));
+        assert!(text.contains("/CIDFontType2"));
+        assert!(text.contains("/CIDToGIDMap /Identity"));
+        assert!(text.contains("/FontFile2"));
+        assert!(text.contains("/F4 6 0 R"));
+
+        // CJK runs switch to the embedded font by glyph id, bold with an outline
+        assert!(text.contains("/F4 20 Tf 2 Tr 0.60 w <00010002> Tj 0 Tr ET"));
+        assert!(text.contains("/F1 11 Tf (notes ) Tj /F4 11 Tf <00010002> Tj /F1 11 Tf ( ?) Tj ET"));
+        // Copied text maps back to the characters
+        assert!(text.contains("<0001> <4E2D>"));
+        assert!(text.contains("<0002> <6587>"));
+
+        let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref"));
+    }
+}
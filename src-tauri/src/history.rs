@@ -0,0 +1,219 @@
+//! Tab activation history: when each site was last shown, most recent
+//! first. Drives the tab switcher's order, `cycle_tab` and `previous_tab`,
+//! and is saved to `tab_history.json` so it survives restarts.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::Website;
+
+/// Presses of `cycle_tab` closer together than this continue the same walk
+/// down the MRU list instead of starting over.
+const CYCLE_TIMEOUT_MS: i64 = 1500;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TabVisit {
+    pub site: String,
+    /// Milliseconds since the Unix epoch
+    pub at: i64,
+}
+
+/// An ongoing `cycle_tab` walk: the MRU order when it started, so switching
+/// tabs along the way doesn't reshuffle it.
+#[derive(Debug)]
+struct Cycle {
+    order: Vec<String>,
+    index: usize,
+    at: i64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct TabHistory {
+    /// One entry per site, most recent first
+    visits: Vec<TabVisit>,
+    #[serde(skip)]
+    cycle: Option<Cycle>,
+}
+
+impl TabHistory {
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize tab history: {}", e))?;
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write tab history: {}", e))
+    }
+
+    pub fn record(&mut self, site: &str, at: i64) {
+        self.visits.retain(|visit| visit.site != site);
+        self.visits.insert(0, TabVisit { site: site.to_string(), at });
+    }
+
+    /// Drop sites that no longer exist.
+    pub fn retain(&mut self, websites: &[Website]) {
+        self.visits.retain(|visit| websites.iter().any(|w| w.id == visit.site));
+        self.cycle = None;
+    }
+
+    pub fn last_active(&self, site: &str) -> Option<i64> {
+        self.visits.iter().find(|visit| visit.site == site).map(|visit| visit.at)
+    }
+
+    /// Sites in most-recently-used order. Sites that were never shown follow
+    /// in settings order; removed sites are skipped.
+    pub fn order<'a>(&self, websites: &'a [Website]) -> Vec<&'a Website> {
+        let mut ordered: Vec<&Website> = self
+            .visits
+            .iter()
+            .filter_map(|visit| websites.iter().find(|w| w.id == visit.site))
+            .collect();
+        for website in websites {
+            if !ordered.iter().any(|w| w.id == website.id) {
+                ordered.push(website);
+            }
+        }
+        ordered
+    }
+
+    /// The most recently used site other than `active`: the "last tab".
+    pub fn previous(&self, websites: &[Website], active: &str) -> Option<String> {
+        self.order(websites).into_iter().find(|w| w.id != active).map(|w| w.id.clone())
+    }
+
+    /// The site `cycle_tab` should show next. The first press goes to the
+    /// previous tab; quick repeated presses walk further back through the
+    /// MRU order as it was when the walk started.
+    pub fn next_in_cycle(&mut self, websites: &[Website], active: &str, now: i64) -> Option<String> {
+        let continuing = self.cycle.as_ref().is_some_and(|cycle| {
+            now - cycle.at < CYCLE_TIMEOUT_MS && cycle.order.get(cycle.index).is_some_and(|id| id == active)
+        });
+
+        if !continuing {
+            let mut order: Vec<String> = self.order(websites).iter().map(|w| w.id.clone()).collect();
+            // The walk starts from the tab on screen, even if it was never recorded
+            if let Some(i) = order.iter().position(|id| id == active) {
+                let id = order.remove(i);
+                order.insert(0, id);
+            }
+            self.cycle = Some(Cycle { order, index: 0, at: now });
+        }
+
+        let cycle = self.cycle.as_mut()?;
+        if cycle.order.len() < 2 {
+            return None;
+        }
+        cycle.index = (cycle.index + 1) % cycle.order.len();
+        cycle.at = now;
+        Some(cycle.order[cycle.index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    fn sites(ids: &[&str]) -> Vec<Website> {
+        ids.iter()
+            .map(|id| Website {
+                id: id.to_string(),
+                name: id.to_string(),
+                url: format!("https://{}.example/", id),
+                emoji: "🌐".to_string(),
+                auto_paste: false,
+                input_selector: None,
+                content_blocking: true,
+                profile: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_order_is_most_recent_first() {
+        let websites = crate::AppSettings::default().websites;
+        let mut history = TabHistory::default();
+        let ids = |history: &TabHistory| history.order(&websites).iter().map(|w| w.id.clone()).collect::<Vec<_>>();
+
+        // Nothing recorded: settings order
+        assert_eq!(ids(&history), ["gemini", "notebooklm"]);
+
+        history.record("notebooklm", 1);
+        assert_eq!(ids(&history), ["notebooklm", "gemini"]);
+
+        history.record("gemini", 2);
+        history.record("removed", 3);
+        assert_eq!(ids(&history), ["gemini", "notebooklm"]);
+        assert_eq!(history.last_active("gemini"), Some(2));
+        assert_eq!(history.last_active("notebooklm"), Some(1));
+
+        // Re-recording moves a site to the front without duplicating it
+        history.record("notebooklm", 4);
+        assert_eq!(ids(&history), ["notebooklm", "gemini"]);
+        assert_eq!(history.visits.len(), 3);
+
+        history.retain(&websites);
+        assert_eq!(history.visits.len(), 2);
+    }
+
+    #[test]
+    fn test_previous_toggles_between_last_two() {
+        let websites = sites(&["a", "b", "c"]);
+        let mut history = TabHistory::default();
+        assert_eq!(history.previous(&websites, "a"), Some("b".to_string()));
+
+        history.record("c", 1);
+        history.record("a", 2);
+        assert_eq!(history.previous(&websites, "a"), Some("c".to_string()));
+        history.record("c", 3);
+        assert_eq!(history.previous(&websites, "c"), Some("a".to_string()));
+
+        assert_eq!(history.previous(&sites(&["a"]), "a"), None);
+    }
+
+    #[test]
+    fn test_cycle_walks_mru_order() {
+        let websites = sites(&["a", "b", "c"]);
+        let mut history = TabHistory::default();
+        history.record("b", 1);
+        history.record("c", 2);
+        history.record("a", 3);
+
+        // Quick presses: a -> c -> b -> a, recording each switch like switch_tab
+        let mut active = "a".to_string();
+        let mut visited = Vec::new();
+        for now in [100, 200, 300] {
+            active = history.next_in_cycle(&websites, &active, now).unwrap();
+            history.record(&active, now);
+            visited.push(active.clone());
+        }
+        assert_eq!(visited, ["c", "b", "a"]);
+
+        // After a pause the walk starts over from the new MRU order
+        history.record("b", 400);
+        assert_eq!(history.next_in_cycle(&websites, "b", 300 + CYCLE_TIMEOUT_MS), Some("a".to_string()));
+
+        // Switching some other way mid-walk also starts over
+        history.record("c", 301 + CYCLE_TIMEOUT_MS);
+        assert_eq!(history.next_in_cycle(&websites, "c", 302 + CYCLE_TIMEOUT_MS), Some("b".to_string()));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new("history");
+        let path = dir.join("tab_history.json");
+
+        let mut history = TabHistory::default();
+        history.record("a", 1);
+        history.record("b", 2);
+        history.save(&path).unwrap();
+
+        let loaded = TabHistory::load(&path).unwrap();
+        assert_eq!(loaded.visits, history.visits);
+        assert!(TabHistory::load(&dir.join("missing.json")).is_none());
+    }
+}
@@ -0,0 +1,108 @@
+//! Typing text into an input of a site window, e.g. a clipboard history
+//! entry picked in the overlay or an expanded snippet.
+
+/// Script evaluated in the site window. `__PIECES__` and `__SELECTOR__` are
+/// replaced with JSON literals before evaluation; a `null` piece stands for
+/// the page's selection and a `null` selector for the focused element.
+const INSERT_SCRIPT: &str = r#"
+(function() {
+    'use strict';
+    var pieces = __PIECES__;
+    var selector = __SELECTOR__;
+
+    function editable(el) {
+        if (!el || el.disabled || el.readOnly) return false;
+        if (el.isContentEditable || el.tagName === 'TEXTAREA') return true;
+        return el.tagName === 'INPUT' && ['text', 'search', 'url', 'email'].indexOf(el.type) !== -1;
+    }
+
+    function selectedText() {
+        var active = document.activeElement;
+        if (active && typeof active.selectionStart === 'number' && active.selectionEnd > active.selectionStart) {
+            return active.value.slice(active.selectionStart, active.selectionEnd);
+        }
+        var selection = window.getSelection();
+        return selection ? selection.toString() : '';
+    }
+
+    // Read before focusing the input moves the selection
+    var selection = selectedText();
+    var text = pieces.map(function(piece) { return piece === null ? selection : piece; }).join('');
+    if (!text) return;
+
+    var el = document.activeElement;
+    if (selector) {
+        try {
+            el = document.querySelector(selector);
+        } catch (e) {
+            console.error('[Peko] Invalid input selector:', e);
+            return;
+        }
+    }
+    if (!editable(el)) {
+        console.warn('[Peko] No input to insert text into');
+        return;
+    }
+
+    el.focus();
+    // Goes through the page's input handling, unlike setting the value
+    document.execCommand('insertText', false, text);
+})();
+"#;
+
+/// Where text sent from another tab goes in a site that doesn't set its own
+/// input selector: the first text field on the page.
+pub const DEFAULT_INPUT_SELECTOR: &str =
+    r#"textarea, [contenteditable="true"], [contenteditable=""], input[type="text"], input[type="search"]"#;
+
+/// Part of the text to insert.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+    Text(String),
+    /// Whatever is selected in the page when the text is inserted. With the
+    /// selection inside the input, the inserted text replaces it.
+    Selection,
+}
+
+pub fn insert_script(pieces: &[Piece], selector: Option<&str>) -> String {
+    let pieces: Vec<serde_json::Value> = pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => serde_json::Value::from(text.as_str()),
+            Piece::Selection => serde_json::Value::Null,
+        })
+        .collect();
+    // The selector goes in first so the inserted text can't contain a placeholder
+    INSERT_SCRIPT
+        .replace("__SELECTOR__", &serde_json::Value::from(selector).to_string())
+        .replace("__PIECES__", &serde_json::Value::from(pieces).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_script_escapes_input() {
+        let script = insert_script(&[Piece::Text("say \"hi\"\n</script>".to_string())], None);
+        assert!(script.contains(r#"var pieces = ["say \"hi\"\n</script>"];"#));
+        assert!(script.contains("var selector = null;"));
+
+        let pieces = [Piece::Text("Explain: ".to_string()), Piece::Selection];
+        let script = insert_script(&pieces, Some("div[contenteditable='true']"));
+        assert!(script.contains(r#"var pieces = ["Explain: ",null];"#));
+        assert!(script.contains(r#"var selector = "div[contenteditable='true']";"#));
+    }
+
+    #[test]
+    fn test_insert_script_keeps_placeholders_in_text() {
+        let pieces = [Piece::Text("__SELECTOR__ and __PIECES__".to_string())];
+        let script = insert_script(&pieces, Some("textarea"));
+        assert!(script.contains(r#"var pieces = ["__SELECTOR__ and __PIECES__"];"#));
+        assert!(script.contains(r#"var selector = "textarea";"#));
+
+        let script = insert_script(&pieces, None);
+        assert!(script.contains(r#"var pieces = ["__SELECTOR__ and __PIECES__"];"#));
+        assert!(script.contains("var selector = null;"));
+    }
+}
@@ -0,0 +1,158 @@
+//! Single-instance enforcement.
+//!
+//! The first process takes an exclusive lock on `peko.lock` in the app data
+//! directory and holds it until it exits; the OS releases it even after a
+//! crash. Later launches fail to take the lock and forward their arguments
+//! to the running instance over the control socket instead.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use crate::Website;
+
+const LOCK_NAME: &str = "peko.lock";
+
+/// Held for the lifetime of the primary instance.
+pub struct InstanceLock {
+    _file: File,
+}
+
+/// Try to become the primary instance. Returns `Ok(None)` if another
+/// instance already holds the lock.
+pub fn try_acquire(data_dir: &Path) -> io::Result<Option<InstanceLock>> {
+    fs::create_dir_all(data_dir)?;
+    let path = data_dir.join(LOCK_NAME);
+    lock_file(&path).map(|file| file.map(|file| InstanceLock { _file: file }))
+}
+
+#[cfg(unix)]
+fn lock_file(path: &Path) -> io::Result<Option<File>> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    // SAFETY: flock only reads the descriptor, which stays open for the call
+    let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if result == 0 {
+        return Ok(Some(file));
+    }
+    let error = io::Error::last_os_error();
+    if error.kind() == io::ErrorKind::WouldBlock {
+        Ok(None)
+    } else {
+        Err(error)
+    }
+}
+
+#[cfg(windows)]
+fn lock_file(path: &Path) -> io::Result<Option<File>> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    // An open handle with no sharing allowed acts as the lock
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    match OpenOptions::new().create(true).truncate(false).write(true).share_mode(0).open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_file(path: &Path) -> io::Result<Option<File>> {
+    OpenOptions::new().create(true).truncate(false).write(true).open(path).map(Some)
+}
+
+/// Whether `url` is on the host the site is configured with.
+pub fn url_on_site(website: &Website, url: &url::Url) -> bool {
+    let Some(host) = url.host_str() else { return false };
+    url::Url::parse(&website.url)
+        .ok()
+        .and_then(|site| site.host_str().map(|h| h.eq_ignore_ascii_case(host)))
+        .unwrap_or(false)
+}
+
+/// Pick the site a URL belongs to by host, e.g. a gemini.google.com link
+/// opens in the Gemini tab.
+pub fn site_for_url<'a>(websites: &'a [Website], url: &url::Url) -> Option<&'a Website> {
+    websites.iter().find(|w| url_on_site(w, url))
+}
+
+/// The site an `activate` request opens: `site` if given, else the one the
+/// URL is on. A URL must be on the site it opens in, so a link can't send a
+/// logged-in site to another origin. None when neither is given.
+pub fn target_site(websites: &[Website], site: Option<&str>, url: Option<&url::Url>) -> Result<Option<String>, String> {
+    match (site, url) {
+        (Some(site), url) => {
+            let website = websites.iter().find(|w| w.id == site)
+                .ok_or_else(|| format!("Unknown site: {}", site))?;
+            match url {
+                Some(url) if !url_on_site(website, url) => Err(format!("{} is not on {}", url, website.name)),
+                _ => Ok(Some(website.id.clone())),
+            }
+        }
+        (None, Some(url)) => site_for_url(websites, url)
+            .map(|w| Some(w.id.clone()))
+            .ok_or_else(|| format!("No site for {}", url)),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Parse a URL passed on the command line, accepting only web URLs.
+pub fn parse_web_url(url: &str) -> Result<url::Url, String> {
+    match url::Url::parse(url) {
+        Ok(url) if url.scheme() == "https" || url.scheme() == "http" => Ok(url),
+        _ => Err(format!("Not a web URL: {}", url)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_second_lock_is_refused() {
+        let dir = TempDir::new("instance");
+        let first = try_acquire(&dir).unwrap();
+        assert!(first.is_some());
+        assert!(try_acquire(&dir).unwrap().is_none());
+
+        drop(first);
+        assert!(try_acquire(&dir).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_site_for_url_matches_host() {
+        let websites = crate::AppSettings::default().websites;
+
+        let url = parse_web_url("https://notebooklm.google.com/notebook/123").unwrap();
+        assert_eq!(site_for_url(&websites, &url).map(|w| w.id.as_str()), Some("notebooklm"));
+
+        let url = parse_web_url("https://example.com/").unwrap();
+        assert!(site_for_url(&websites, &url).is_none());
+
+        assert!(parse_web_url("javascript:alert(1)").is_err());
+    }
+
+    #[test]
+    fn test_target_site_keeps_urls_on_their_site() {
+        let websites = crate::AppSettings::default().websites;
+        let url = |url: &str| parse_web_url(url).unwrap();
+
+        assert_eq!(target_site(&websites, Some("gemini"), None), Ok(Some("gemini".to_string())));
+        assert_eq!(
+            target_site(&websites, Some("gemini"), Some(&url("https://gemini.google.com/app/1"))),
+            Ok(Some("gemini".to_string()))
+        );
+        assert_eq!(
+            target_site(&websites, None, Some(&url("https://notebooklm.google.com/"))),
+            Ok(Some("notebooklm".to_string()))
+        );
+        assert_eq!(target_site(&websites, None, None), Ok(None));
+
+        assert!(target_site(&websites, Some("gemini"), Some(&url("https://evil.example/"))).is_err());
+        assert!(target_site(&websites, Some("gemini"), Some(&url("https://notebooklm.google.com/"))).is_err());
+        assert!(target_site(&websites, None, Some(&url("https://evil.example/"))).is_err());
+        assert!(target_site(&websites, Some("missing"), None).is_err());
+    }
+}
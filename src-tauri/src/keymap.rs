@@ -0,0 +1,191 @@
+//! In-app keyboard shortcuts (menu accelerators).
+//!
+//! Every command in the action registry can be bound, as can the tab items
+//! by position; most come with a default accelerator. `AppSettings::keymap`
+//! only stores the user's changes, keyed by action id; an empty string
+//! unbinds the action. Defaults can change between versions without
+//! touching saved settings.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{actions, shortcuts};
+
+/// Tab items are bound by position rather than by site id.
+const TAB_BINDINGS: &[(&str, &str, &str)] = &[
+    ("tab_1", "Tab 1", "CmdOrCtrl+1"),
+    ("tab_2", "Tab 2", "CmdOrCtrl+2"),
+    ("tab_3", "Tab 3", "CmdOrCtrl+3"),
+    ("tab_4", "Tab 4", "CmdOrCtrl+4"),
+    ("tab_5", "Tab 5", "CmdOrCtrl+5"),
+    ("tab_6", "Tab 6", "CmdOrCtrl+6"),
+    ("tab_7", "Tab 7", "CmdOrCtrl+7"),
+    ("tab_8", "Tab 8", "CmdOrCtrl+8"),
+    ("tab_9", "Tab 9", "CmdOrCtrl+9"),
+];
+
+/// Bindable actions: (id, label, default accelerator). Every registered
+/// command, then the tab items.
+fn bindings() -> impl Iterator<Item = (&'static str, &'static str, Option<&'static str>)> {
+    actions::COMMANDS
+        .iter()
+        .map(|command| (command.id, command.label, command.accelerator))
+        .chain(TAB_BINDINGS.iter().map(|(id, label, default)| (*id, *label, Some(*default))))
+}
+
+fn default_for(action: &str) -> Option<Option<&'static str>> {
+    bindings().find(|(id, _, _)| *id == action).map(|(_, _, default)| default)
+}
+
+/// One row of the resolved keymap, as returned by `get_keymap`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub action: String,
+    pub label: String,
+    pub accelerator: Option<String>,
+    pub default: Option<String>,
+}
+
+/// The keymap with the user's overrides applied.
+pub struct Keymap<'a> {
+    overrides: &'a BTreeMap<String, String>,
+}
+
+impl<'a> Keymap<'a> {
+    pub fn new(overrides: &'a BTreeMap<String, String>) -> Self {
+        Keymap { overrides }
+    }
+
+    /// Accelerator for an action, or `None` if it is unbound.
+    pub fn get(&self, action: &str) -> Option<&'a str> {
+        let accelerator = match self.overrides.get(action) {
+            Some(accelerator) => accelerator.as_str(),
+            None => default_for(action)??,
+        };
+        Some(accelerator).filter(|a| !a.is_empty())
+    }
+
+    /// Accelerator for the tab at `index` (0-based).
+    pub fn tab(&self, index: usize) -> Option<&'a str> {
+        self.get(&format!("tab_{}", index + 1))
+    }
+
+    pub fn bindings(&self) -> Vec<KeyBinding> {
+        bindings()
+            .map(|(id, label, default)| KeyBinding {
+                action: id.to_string(),
+                label: label.to_string(),
+                accelerator: self.get(id).map(String::from),
+                default: default.map(String::from),
+            })
+            .collect()
+    }
+
+    /// Accelerators the menu binds with `tab_count` tabs.
+    pub fn accelerators(&self, tab_count: usize) -> Vec<String> {
+        bindings()
+            .filter(|(id, _, _)| match id.strip_prefix("tab_") {
+                Some(n) => n.parse::<usize>().is_ok_and(|n| n <= tab_count),
+                None => true,
+            })
+            .filter_map(|(id, _, _)| self.get(id))
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Turn a full action -> accelerator map from the settings UI into the
+/// overrides to store, rejecting unknown actions, invalid accelerators and
+/// accelerators bound to two actions.
+pub fn overrides_from(keymap: &HashMap<String, Option<String>>) -> Result<BTreeMap<String, String>, String> {
+    let mut overrides = BTreeMap::new();
+    for (action, accelerator) in keymap {
+        let Some(default) = default_for(action) else {
+            return Err(format!("Unknown action: {}", action));
+        };
+        let accelerator = accelerator.as_deref().map(str::trim).unwrap_or_default();
+        if accelerator != default.unwrap_or_default() {
+            overrides.insert(action.clone(), accelerator.to_string());
+        }
+    }
+    validate(&overrides)?;
+    Ok(overrides)
+}
+
+pub fn validate(overrides: &BTreeMap<String, String>) -> Result<(), String> {
+    let keymap = Keymap::new(overrides);
+    let mut seen: HashMap<u32, &str> = HashMap::new();
+    for (id, label, _) in bindings() {
+        let Some(accelerator) = keymap.get(id) else {
+            continue;
+        };
+        let shortcut = shortcuts::parse(accelerator)?;
+        if let Some(other) = seen.insert(shortcut.id(), label) {
+            return Err(format!("{} is used by both {} and {}", accelerator, other, label));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_valid() {
+        assert!(validate(&BTreeMap::new()).is_ok());
+        let keymap = BTreeMap::new();
+        let keymap = Keymap::new(&keymap);
+        assert_eq!(keymap.get("toggle_notes"), Some("CmdOrCtrl+N"));
+        assert_eq!(keymap.tab(0), Some("CmdOrCtrl+1"));
+        assert_eq!(keymap.tab(8), Some("CmdOrCtrl+9"));
+        assert_eq!(keymap.tab(9), None);
+        assert_eq!(keymap.get("missing"), None);
+        assert_eq!(keymap.get("export_notes_pdf"), None);
+    }
+
+    #[test]
+    fn test_overrides_apply_and_unbind() {
+        let overrides = BTreeMap::from([
+            ("toggle_notes".to_string(), "CmdOrCtrl+'".to_string()),
+            ("cycle_tab".to_string(), String::new()),
+        ]);
+        let keymap = Keymap::new(&overrides);
+        assert_eq!(keymap.get("toggle_notes"), Some("CmdOrCtrl+'"));
+        assert_eq!(keymap.get("cycle_tab"), None);
+        assert!(!keymap.accelerators(9).contains(&"CmdOrCtrl+Tab".to_string()));
+        assert!(keymap.accelerators(2).contains(&"CmdOrCtrl+2".to_string()));
+        assert!(!keymap.accelerators(2).contains(&"CmdOrCtrl+3".to_string()));
+        assert!(validate(&overrides).is_ok());
+    }
+
+    #[test]
+    fn test_overrides_from_keeps_only_changes() {
+        let input = HashMap::from([
+            ("settings".to_string(), Some("CmdOrCtrl+Comma".to_string())),
+            ("toggle_notes".to_string(), Some("CmdOrCtrl+'".to_string())),
+            ("auto_paste".to_string(), None),
+            ("export_notes_pdf".to_string(), Some("CmdOrCtrl+Shift+E".to_string())),
+            ("export_notes_html".to_string(), None),
+        ]);
+        let overrides = overrides_from(&input).unwrap();
+        assert_eq!(
+            overrides,
+            BTreeMap::from([
+                ("auto_paste".to_string(), String::new()),
+                ("export_notes_pdf".to_string(), "CmdOrCtrl+Shift+E".to_string()),
+                ("toggle_notes".to_string(), "CmdOrCtrl+'".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_invalid_keymaps_are_rejected() {
+        let one = |action: &str, accelerator: &str| HashMap::from([(action.to_string(), Some(accelerator.to_string()))]);
+        assert!(overrides_from(&one("launch_missiles", "CmdOrCtrl+M")).is_err());
+        assert!(overrides_from(&one("toggle_notes", "CmdOrCtrl+Banana")).is_err());
+        // Clashes with the default for Back, even when written differently
+        let error = overrides_from(&one("toggle_notes", "cmdorctrl+[")).unwrap_err();
+        assert!(error.contains("Back") && error.contains("Toggle Notes"), "{}", error);
+    }
+}
@@ -0,0 +1,316 @@
+//! App lock: hides every window behind a passphrase prompt, on command or
+//! after a stretch without activity.
+//!
+//! The passphrase is kept as an Argon2 hash (PHC string) in
+//! `AppSettings::lock_passphrase_hash`; setting one turns the lock on.
+//! Activity is any window being focused, moved or resized, plus keyboard
+//! and pointer input in Peko's windows. Input comes from the OS rather than
+//! from the pages, which could fake it to hold off the lock.
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// Label of the lock window.
+pub const WINDOW_LABEL: &str = "lock";
+
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// How often the idle timer checks for inactivity.
+pub const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Longest wait after wrong passphrases; the wait doubles from a second
+/// with each one.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+fn backoff(failures: u32) -> Duration {
+    Duration::from_secs(1u64 << failures.saturating_sub(1).min(6)).min(MAX_BACKOFF)
+}
+
+pub fn hash_passphrase(passphrase: &str) -> Result<String, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("The passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+    let mut salt = [0u8; 16];
+    getrandom::getrandom(&mut salt).map_err(|e| format!("Failed to generate salt: {}", e))?;
+    let salt = SaltString::encode_b64(&salt).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash passphrase: {}", e))
+}
+
+/// Whether `passphrase` matches `hash`. A hash that doesn't parse matches
+/// nothing.
+pub fn verify_passphrase(passphrase: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(passphrase.as_bytes(), &hash).is_ok())
+}
+
+struct Inner {
+    locked: bool,
+    /// Windows that were visible when the app locked, shown again on unlock
+    hidden: Vec<String>,
+    last_activity: Instant,
+    /// An unlock attempt is checking its passphrase
+    attempting: bool,
+    /// Wrong passphrases since the last unlock
+    failures: u32,
+    /// No attempt is taken before this, after a wrong passphrase
+    retry_at: Option<Instant>,
+}
+
+pub struct LockState(Mutex<Inner>);
+
+impl Default for LockState {
+    fn default() -> Self {
+        LockState(Mutex::new(Inner {
+            locked: false,
+            hidden: Vec::new(),
+            last_activity: Instant::now(),
+            attempting: false,
+            failures: 0,
+            retry_at: None,
+        }))
+    }
+}
+
+impl LockState {
+    pub fn is_locked(&self) -> bool {
+        self.0.lock().unwrap().locked
+    }
+
+    pub fn touch(&self) {
+        self.0.lock().unwrap().last_activity = Instant::now();
+    }
+
+    /// Whether the app should lock for being idle at least `limit`.
+    pub fn idle_expired(&self, limit: Duration) -> bool {
+        let inner = self.0.lock().unwrap();
+        !inner.locked && inner.last_activity.elapsed() >= limit
+    }
+
+    /// Mark the app locked, with the windows that were hidden for it.
+    /// Returns false if it already was.
+    pub fn lock(&self, hidden: Vec<String>) -> bool {
+        let mut inner = self.0.lock().unwrap();
+        if inner.locked {
+            return false;
+        }
+        inner.locked = true;
+        inner.hidden = hidden;
+        true
+    }
+
+    /// Start checking a passphrase. Attempts run one at a time and not
+    /// while backing off from a wrong one; the error is how long to wait.
+    pub fn start_attempt(&self) -> Result<(), Duration> {
+        let mut inner = self.0.lock().unwrap();
+        let wait = inner.retry_at.map_or(Duration::ZERO, |at| at.saturating_duration_since(Instant::now()));
+        if inner.attempting || !wait.is_zero() {
+            return Err(wait.max(Duration::from_secs(1)));
+        }
+        inner.attempting = true;
+        Ok(())
+    }
+
+    /// End the attempt started with `start_attempt`. A wrong passphrase
+    /// makes the next attempt wait longer. Returns `verified`.
+    pub fn finish_attempt(&self, verified: bool) -> bool {
+        let mut inner = self.0.lock().unwrap();
+        inner.attempting = false;
+        if verified {
+            inner.failures = 0;
+            inner.retry_at = None;
+        } else {
+            inner.failures = inner.failures.saturating_add(1);
+            inner.retry_at = Some(Instant::now() + backoff(inner.failures));
+        }
+        verified
+    }
+
+    /// Mark the app unlocked, returning the windows to show again.
+    pub fn unlock(&self) -> Vec<String> {
+        let mut inner = self.0.lock().unwrap();
+        inner.locked = false;
+        inner.last_activity = Instant::now();
+        std::mem::take(&mut inner.hidden)
+    }
+}
+
+/// Count input in `window` as activity. WebKitGTK reports it per webview;
+/// on Windows and macOS `recent_input` asks the OS instead.
+pub fn watch_input(window: &WebviewWindow) {
+    let app = window.app_handle().clone();
+    if let Err(e) = window.with_webview(move |webview| platform::watch_input(webview, app)) {
+        log::warn!("Failed to watch input in {}: {}", window.label(), e);
+    }
+}
+
+/// Whether there was input within `within` while one of Peko's windows has
+/// focus, where the OS can say when the last input was.
+pub fn recent_input(app: &AppHandle, within: Duration) -> bool {
+    platform::since_last_input().is_some_and(|since| since < within)
+        && app.webview_windows().values().any(|window| window.is_focused().unwrap_or(false))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::LockState;
+    use std::time::Duration;
+    use tauri::{AppHandle, Manager};
+    use webkit2gtk::glib::prelude::*;
+
+    const INPUT_SIGNALS: &[&str] = &["key-press-event", "button-press-event", "scroll-event", "motion-notify-event"];
+
+    pub fn watch_input(webview: tauri::webview::PlatformWebview, app: AppHandle) {
+        let webview = webview.inner();
+        for signal in INPUT_SIGNALS {
+            let app = app.clone();
+            webview.connect_local(signal, false, move |_| {
+                app.state::<LockState>().touch();
+                // Let the page have the event too
+                Some(false.to_value())
+            });
+        }
+    }
+
+    pub fn since_last_input() -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    #[repr(C)]
+    struct LastInputInfo {
+        size: u32,
+        time: u32,
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetLastInputInfo(info: *mut LastInputInfo) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetTickCount() -> u32;
+    }
+
+    pub fn watch_input(_webview: tauri::webview::PlatformWebview, _app: AppHandle) {}
+
+    pub fn since_last_input() -> Option<Duration> {
+        let mut info = LastInputInfo { size: std::mem::size_of::<LastInputInfo>() as u32, time: 0 };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // Both are milliseconds since boot, wrapping every 49.7 days
+        let elapsed = unsafe { GetTickCount() }.wrapping_sub(info.time);
+        Some(Duration::from_millis(elapsed.into()))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    /// `kCGEventSourceStateHIDSystemState`
+    const HID_SYSTEM_STATE: i32 = 1;
+    /// `kCGAnyInputEventType`
+    const ANY_INPUT_EVENT: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+
+    pub fn watch_input(_webview: tauri::webview::PlatformWebview, _app: AppHandle) {}
+
+    pub fn since_last_input() -> Option<Duration> {
+        let seconds = unsafe { CGEventSourceSecondsSinceLastEventType(HID_SYSTEM_STATE, ANY_INPUT_EVENT) };
+        Duration::try_from_secs_f64(seconds).ok()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    pub fn watch_input(_webview: tauri::webview::PlatformWebview, _app: AppHandle) {}
+
+    pub fn since_last_input() -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase_hash() {
+        assert!(hash_passphrase("short").is_err());
+
+        let hash = hash_passphrase("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_passphrase("correct horse", &hash));
+        assert!(!verify_passphrase("correct horsE", &hash));
+        assert!(!verify_passphrase("correct horse", "not a hash"));
+        // Salted: the same passphrase hashes differently
+        assert_ne!(hash, hash_passphrase("correct horse").unwrap());
+    }
+
+    #[test]
+    fn test_lock_state() {
+        let state = LockState::default();
+        assert!(!state.is_locked());
+        assert!(!state.idle_expired(Duration::from_secs(60)));
+        assert!(state.idle_expired(Duration::ZERO));
+
+        assert!(state.lock(vec!["gemini".to_string()]));
+        assert!(!state.lock(Vec::new()));
+        assert!(state.is_locked());
+        assert!(!state.idle_expired(Duration::ZERO));
+
+        assert_eq!(state.unlock(), ["gemini"]);
+        assert!(!state.is_locked());
+        assert!(state.unlock().is_empty());
+    }
+
+    #[test]
+    fn test_unlock_attempts() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(4));
+        assert_eq!(backoff(100), MAX_BACKOFF);
+
+        let state = LockState::default();
+        state.start_attempt().unwrap();
+        // One at a time
+        assert!(state.start_attempt().is_err());
+        assert!(!state.finish_attempt(false));
+
+        // Backing off from the wrong passphrase
+        assert!(state.start_attempt().is_err());
+        state.0.lock().unwrap().retry_at = Some(Instant::now());
+        state.start_attempt().unwrap();
+        state.finish_attempt(false);
+        assert_eq!(state.0.lock().unwrap().failures, 2);
+        let wait = state.start_attempt().unwrap_err();
+        assert!(wait > Duration::from_secs(1) && wait <= backoff(2));
+
+        state.0.lock().unwrap().retry_at = None;
+        state.start_attempt().unwrap();
+        assert!(state.finish_attempt(true));
+        assert_eq!(state.0.lock().unwrap().failures, 0);
+        state.start_attempt().unwrap();
+    }
+
+}
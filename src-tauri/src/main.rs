@@ -1,6 +1,11 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use peko_lib::cli::{self, Outcome};
+
 fn main() {
-    peko_lib::run()
+    match cli::main() {
+        Outcome::Exit(code) => std::process::exit(code),
+        Outcome::Launch(options) => peko_lib::run_with(options),
+    }
 }
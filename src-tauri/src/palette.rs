@@ -0,0 +1,259 @@
+//! Command palette: fuzzy search over commands, sites, snippets, notes
+//! documents and recently visited URLs.
+//!
+//! Items are built fresh for every query from the action registry and the
+//! current settings. Picking one sends its `PaletteAction` back to
+//! `run_palette_action`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::actions;
+use crate::keymap::Keymap;
+use crate::AppSettings;
+
+/// How many recently visited pages the palette offers.
+const MAX_RECENT_URLS: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaletteAction {
+    Command { id: String },
+    SwitchTab { site: String },
+    OpenNotes { doc_id: String },
+    OpenUrl { site: String, url: String },
+    InsertSnippet { id: String },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PaletteItem {
+    pub title: String,
+    /// Secondary text, e.g. the URL of a recent page
+    pub detail: Option<String>,
+    pub shortcut: Option<String>,
+    pub action: PaletteAction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecentUrl {
+    pub site: String,
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// Pages loaded in site windows this session, most recent first.
+#[derive(Default)]
+pub struct RecentUrls(Mutex<VecDeque<RecentUrl>>);
+
+impl RecentUrls {
+    pub fn record(&self, site: &str, url: &str) {
+        let mut recent = self.0.lock().unwrap();
+        let title = recent
+            .iter()
+            .position(|r| r.url == url)
+            .and_then(|i| recent.remove(i))
+            .and_then(|r| r.title);
+        recent.push_front(RecentUrl { site: site.to_string(), url: url.to_string(), title });
+        recent.truncate(MAX_RECENT_URLS);
+    }
+
+    /// Titles arrive after the page load, so attach them afterwards.
+    pub fn set_title(&self, url: &str, title: &str) {
+        if let Some(entry) = self.0.lock().unwrap().iter_mut().find(|r| r.url == url) {
+            entry.title = Some(title.to_string());
+        }
+    }
+
+    pub fn list(&self) -> Vec<RecentUrl> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Every palette entry, in the order shown for an empty query.
+pub fn items(settings: &AppSettings, notes_documents: &[&str], recent: &[RecentUrl]) -> Vec<PaletteItem> {
+    let keymap = Keymap::new(&settings.keymap);
+    let mut items = Vec::new();
+
+    for (i, website) in settings.websites.iter().enumerate() {
+        items.push(PaletteItem {
+            title: format!("{} {}", website.emoji, website.name),
+            detail: Some(website.url.clone()),
+            shortcut: keymap.tab(i).map(String::from),
+            action: PaletteAction::SwitchTab { site: website.id.clone() },
+        });
+    }
+
+    for command in actions::COMMANDS {
+        // Opening the palette from the palette does nothing useful
+        if command.id == "show_command_palette" {
+            continue;
+        }
+        items.push(PaletteItem {
+            title: command.label.to_string(),
+            detail: None,
+            shortcut: keymap.get(command.id).map(String::from),
+            action: PaletteAction::Command { id: command.id.to_string() },
+        });
+    }
+
+    for snippet in &settings.snippets {
+        items.push(PaletteItem {
+            title: format!("Snippet: {}", snippet.name),
+            detail: snippet.body.lines().map(str::trim).find(|line| !line.is_empty()).map(String::from),
+            shortcut: snippet.accelerator.clone(),
+            action: PaletteAction::InsertSnippet { id: snippet.id.clone() },
+        });
+    }
+
+    for doc_id in notes_documents {
+        items.push(PaletteItem {
+            title: format!("Open Notes: {}", doc_id),
+            detail: None,
+            shortcut: None,
+            action: PaletteAction::OpenNotes { doc_id: doc_id.to_string() },
+        });
+    }
+
+    for page in recent {
+        items.push(PaletteItem {
+            title: page.title.clone().unwrap_or_else(|| page.url.clone()),
+            detail: Some(page.url.clone()),
+            shortcut: None,
+            action: PaletteAction::OpenUrl { site: page.site.clone(), url: page.url.clone() },
+        });
+    }
+
+    items
+}
+
+/// Items matching `query`, best first. An empty query keeps the original
+/// order.
+pub fn search(items: Vec<PaletteItem>, query: &str, limit: usize) -> Vec<PaletteItem> {
+    let query = query.trim();
+    if query.is_empty() {
+        return items.into_iter().take(limit).collect();
+    }
+
+    let mut scored: Vec<(i32, PaletteItem)> = items
+        .into_iter()
+        .filter_map(|item| {
+            let title = fuzzy_score(query, &item.title);
+            // Matching the detail (a URL) counts for less than the title
+            let detail = item.detail.as_deref().and_then(|d| fuzzy_score(query, d)).map(|s| s / 2);
+            title.max(detail).map(|score| (score, item))
+        })
+        .collect();
+    // Stable, so equal scores keep the registry order
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().take(limit).map(|(_, item)| item).collect()
+}
+
+/// Score `text` against `query` as a case-insensitive subsequence match.
+/// Consecutive matches and matches at the start of a word score higher.
+/// `None` if some query character doesn't occur in order.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        let offset = text[pos..].iter().position(|c| c.to_lowercase().eq(std::iter::once(q)))?;
+        let i = pos + offset;
+
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == i) {
+            score += 5;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 8;
+        }
+        // Skipped characters cost a little so tighter matches win
+        score -= offset.min(10) as i32;
+
+        previous = Some(i);
+        pos = i + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("tn", "Toggle Notes").is_some());
+        assert!(fuzzy_score("nt", "Toggle Notes").is_some());
+        assert!(fuzzy_score("xyz", "Toggle Notes").is_none());
+        assert!(fuzzy_score("notes", "Toggle Notes") > fuzzy_score("notes", "Show Tab Switcher Now Extra Settings"));
+        // Word starts beat matches in the middle of words
+        assert!(fuzzy_score("tn", "Toggle Notes") > fuzzy_score("tn", "Settings"));
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_items_cover_sites_commands_snippets_notes_and_recent() {
+        let mut settings = AppSettings::default();
+        settings.snippets.push(crate::Snippet {
+            id: "review".to_string(),
+            name: "Code review".to_string(),
+            body: "\nReview this code:\n{{selection}}".to_string(),
+            sites: Vec::new(),
+            accelerator: Some("CmdOrCtrl+Alt+R".to_string()),
+        });
+        let recent = vec![RecentUrl {
+            site: "gemini".to_string(),
+            url: "https://gemini.google.com/app/abc".to_string(),
+            title: Some("Rust lifetimes".to_string()),
+        }];
+        let items = items(&settings, &["notes"], &recent);
+
+        assert_eq!(items[0].action, PaletteAction::SwitchTab { site: "gemini".to_string() });
+        assert_eq!(items[0].shortcut.as_deref(), Some("CmdOrCtrl+1"));
+        assert!(items.iter().any(|i| i.action == PaletteAction::Command { id: "toggle_notes".to_string() }
+            && i.shortcut.as_deref() == Some("CmdOrCtrl+N")));
+        assert!(!items.iter().any(|i| i.action == PaletteAction::Command { id: "show_command_palette".to_string() }));
+        assert!(items.iter().any(|i| i.action == PaletteAction::OpenNotes { doc_id: "notes".to_string() }));
+        let snippet = items.iter().find(|i| i.action == PaletteAction::InsertSnippet { id: "review".to_string() }).unwrap();
+        assert_eq!(snippet.title, "Snippet: Code review");
+        assert_eq!(snippet.detail.as_deref(), Some("Review this code:"));
+        assert_eq!(snippet.shortcut.as_deref(), Some("CmdOrCtrl+Alt+R"));
+
+        let hits = search(items, "lifetimes", 10);
+        assert_eq!(hits[0].title, "Rust lifetimes");
+    }
+
+    #[test]
+    fn test_search_ranks_and_limits() {
+        let items = items(&AppSettings::default(), &[], &[]);
+        let total = items.len();
+
+        assert_eq!(search(items.clone(), "", 5).len(), 5);
+        assert_eq!(search(items.clone(), "  ", 100).len(), total);
+
+        let hits = search(items.clone(), "back", 10);
+        assert_eq!(hits[0].action, PaletteAction::Command { id: "go_back".to_string() });
+        assert!(search(items, "qqqq", 10).is_empty());
+    }
+
+    #[test]
+    fn test_recent_urls_dedup_and_keep_titles() {
+        let recent = RecentUrls::default();
+        recent.record("gemini", "https://a.example/");
+        recent.set_title("https://a.example/", "A");
+        recent.record("gemini", "https://b.example/");
+        recent.record("gemini", "https://a.example/");
+
+        let list = recent.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].url, "https://a.example/");
+        assert_eq!(list[0].title.as_deref(), Some("A"));
+
+        for i in 0..MAX_RECENT_URLS + 5 {
+            recent.record("gemini", &format!("https://{}.example/", i));
+        }
+        assert_eq!(recent.list().len(), MAX_RECENT_URLS);
+    }
+}
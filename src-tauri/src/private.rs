@@ -0,0 +1,85 @@
+//! Private windows: a site opened without its profile, on a store that is
+//! thrown away when the window closes.
+//!
+//! The webview runs in incognito mode, so cookies and storage stay in
+//! memory. WebView2 still needs a user data folder, so each window also
+//! gets a directory under `private/`, deleted when the window is destroyed
+//! or, if Peko didn't get to it, at the next startup.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Window labels of private windows start with this.
+pub const LABEL_PREFIX: &str = "private_";
+
+/// Directory of the private windows' data in the app data directory.
+const DIR: &str = "private";
+
+static NEXT_WINDOW: AtomicU32 = AtomicU32::new(1);
+
+/// A label for a new private window, unique for this run.
+pub fn next_label() -> String {
+    format!("{}{}", LABEL_PREFIX, NEXT_WINDOW.fetch_add(1, Ordering::SeqCst))
+}
+
+pub fn is_private(label: &str) -> bool {
+    label.starts_with(LABEL_PREFIX)
+}
+
+pub fn data_dir(app_data_dir: &Path, label: &str) -> PathBuf {
+    app_data_dir.join(DIR).join(label)
+}
+
+/// Delete the data of every private window, left behind by a crash. No
+/// private window may be open.
+pub fn remove_all(app_data_dir: &Path) {
+    match fs::remove_dir_all(app_data_dir.join(DIR)) {
+        Ok(()) => log::info!("Removed data of private windows from the last run"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Failed to remove data of private windows: {}", e),
+    }
+}
+
+/// Delete a closed private window's data. WebView2 may hold on to its files
+/// for a moment after the window is gone, so this retries for a few
+/// seconds; whatever is left is removed at the next startup.
+pub async fn remove(dir: PathBuf) {
+    for _ in 0..5 {
+        match fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            }
+            _ => return,
+        }
+    }
+    log::warn!("Failed to remove data of private window {}", dir.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_private_windows() {
+        let first = next_label();
+        let second = next_label();
+        assert_ne!(first, second);
+        assert!(is_private(&first));
+        assert!(!is_private("gemini"));
+
+        let dir = TempDir::new("private");
+        fs::create_dir_all(data_dir(&dir, &first)).unwrap();
+        fs::create_dir_all(data_dir(&dir, &second)).unwrap();
+        fs::write(dir.join("settings.json"), "{}").unwrap();
+
+        tauri::async_runtime::block_on(remove(data_dir(&dir, &first)));
+        assert!(!data_dir(&dir, &first).exists());
+        assert!(data_dir(&dir, &second).exists());
+
+        remove_all(&dir);
+        assert!(!dir.join(DIR).exists());
+        assert!(dir.join("settings.json").exists());
+    }
+}
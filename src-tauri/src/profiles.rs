@@ -0,0 +1,187 @@
+//! Profiles (containers): named sets of cookies, storage and cache that
+//! sites share. Sites in one profile share logins, e.g. one Google account
+//! for Gemini and NotebookLM; separate profiles keep work and personal
+//! accounts apart.
+//!
+//! Each site names its profile in `Website::profile`. Sites saved before
+//! profiles existed each get a profile of their own, which takes over the
+//! site's data.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Website;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    /// Use WebKit's default store on macOS, where every site kept its data
+    /// before profiles existed, instead of a store of the profile's own
+    #[serde(default)]
+    pub default_store: bool,
+}
+
+/// Whether `id` is safe as a directory name: profile ids name the
+/// profile's data directory.
+pub fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Give every site without a profile, or with an invalid profile id, one of
+/// its own, named after the site, and create the profiles sites refer to
+/// that don't exist. Profiles with invalid ids, e.g. from a hand-edited
+/// settings file, are dropped. Returns whether anything changed.
+pub fn assign(websites: &mut [Website], profiles: &mut Vec<Profile>, default_store: bool) -> bool {
+    let count = profiles.len();
+    profiles.retain(|p| valid_id(&p.id));
+    let mut changed = profiles.len() != count;
+    for website in websites.iter_mut() {
+        if !valid_id(&website.profile) {
+            let base: String = website.id
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+                .collect();
+            let mut id = base.clone();
+            let mut n = 2;
+            while profiles.iter().any(|p| p.id == id) {
+                id = format!("{}_{}", base, n);
+                n += 1;
+            }
+            profiles.push(Profile { id: id.clone(), name: website.name.clone(), default_store });
+            website.profile = id;
+            changed = true;
+        } else if !profiles.iter().any(|p| p.id == website.profile) {
+            profiles.push(Profile {
+                id: website.profile.clone(),
+                name: website.profile.clone(),
+                default_store,
+            });
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// The profile a site uses.
+pub fn of<'a>(profiles: &'a [Profile], website: &Website) -> Option<&'a Profile> {
+    profiles.iter().find(|p| p.id == website.profile)
+}
+
+/// Check a profile list, and that every site's profile is in it.
+pub fn validate(profiles: &[Profile], websites: &[Website]) -> Result<(), String> {
+    let mut ids: Vec<&str> = Vec::new();
+    for profile in profiles {
+        if !valid_id(&profile.id) {
+            return Err(format!("Invalid profile id: {}", profile.id));
+        }
+        if profile.name.trim().is_empty() {
+            return Err(format!("Profile \"{}\" has no name", profile.id));
+        }
+        if ids.contains(&profile.id.as_str()) {
+            return Err(format!("Duplicate profile id: {}", profile.id));
+        }
+        ids.push(&profile.id);
+    }
+    for website in websites {
+        if !ids.contains(&website.profile.as_str()) {
+            return Err(format!("Unknown profile for {}: {}", website.name, website.profile));
+        }
+    }
+    Ok(())
+}
+
+/// Identifier of the profile's WebKit store on macOS 14 and later, the same
+/// on every run (FNV-1a of the profile id).
+pub fn store_identifier(profile: &str) -> [u8; 16] {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    profile
+        .bytes()
+        .fold(OFFSET, |hash, byte| (hash ^ byte as u128).wrapping_mul(PRIME))
+        .to_be_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(id: &str, profile: &str) -> Website {
+        Website {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            url: format!("https://{}.example.com", id),
+            emoji: "✨".to_string(),
+            auto_paste: false,
+            input_selector: None,
+            content_blocking: true,
+            profile: profile.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_assign() {
+        let mut profiles = vec![Profile { id: "work".to_string(), name: "Work".to_string(), default_store: false }];
+        let mut websites = vec![site("gemini", ""), site("work", ""), site("chat", "work"), site("notes", "personal")];
+        assert!(assign(&mut websites, &mut profiles, true));
+
+        let ids: Vec<&str> = websites.iter().map(|w| w.profile.as_str()).collect();
+        assert_eq!(ids, ["gemini", "work_2", "work", "personal"]);
+        assert_eq!(profiles.len(), 4);
+        assert_eq!(profiles[1], Profile { id: "gemini".to_string(), name: "GEMINI".to_string(), default_store: true });
+        assert_eq!(profiles[3].name, "personal");
+        assert!(validate(&profiles, &websites).is_ok());
+
+        assert!(!assign(&mut websites, &mut profiles, true));
+        assert_eq!(profiles.len(), 4);
+
+        // Site ids may contain anything; profile ids end up in paths
+        let mut websites = vec![site("../x y", "")];
+        assign(&mut websites, &mut profiles, false);
+        assert_eq!(websites[0].profile, "___x_y");
+    }
+
+    #[test]
+    fn test_assign_replaces_invalid_profile_ids() {
+        let json = r#"{
+            "websites": [
+                { "id": "gemini", "name": "Gemini", "url": "https://gemini.google.com/app", "emoji": "✨", "profile": ".." },
+                { "id": "chat", "name": "Chat", "url": "https://chatgpt.com", "emoji": "💬", "profile": "a/b" }
+            ],
+            "profiles": [{ "id": "..", "name": "Up" }, { "id": "gemini", "name": "Taken" }],
+            "active_tab": "gemini"
+        }"#;
+        let mut settings: crate::AppSettings = serde_json::from_str(json).unwrap();
+        assert!(validate(&settings.profiles, &settings.websites).is_err());
+
+        assert!(assign(&mut settings.websites, &mut settings.profiles, true));
+        assert!(validate(&settings.profiles, &settings.websites).is_ok());
+        let ids: Vec<&str> = settings.websites.iter().map(|w| w.profile.as_str()).collect();
+        assert_eq!(ids, ["gemini_2", "chat"]);
+        assert!(!settings.profiles.iter().any(|p| p.id == ".."));
+
+        let app_data_dir = std::path::Path::new("/data/peko");
+        for website in &settings.websites {
+            let dir = crate::sitedata::data_dir(app_data_dir, &website.profile);
+            assert_eq!(dir.parent(), Some(app_data_dir.join("profiles").as_path()));
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let profile = |id: &str, name: &str| Profile { id: id.to_string(), name: name.to_string(), default_store: false };
+        let websites = vec![site("gemini", "google")];
+
+        assert!(validate(&[profile("google", "Google")], &websites).is_ok());
+        assert!(validate(&[profile("google", "Google"), profile("unused", "Unused")], &websites).is_ok());
+        assert!(validate(&[profile("work", "Work")], &websites).is_err());
+        assert!(validate(&[profile("google", "Google"), profile("google", "Other")], &websites).is_err());
+        assert!(validate(&[profile("google", " ")], &websites).is_err());
+        assert!(validate(&[profile("google", "Google"), profile("../etc", "Evil")], &websites).is_err());
+    }
+
+    #[test]
+    fn test_store_identifier() {
+        assert_eq!(store_identifier("work"), store_identifier("work"));
+        assert_ne!(store_identifier("work"), store_identifier("personal"));
+    }
+}
@@ -0,0 +1,269 @@
+//! Full-text search over notes documents.
+//!
+//! The index is a simple inverted index (term -> line postings) that is
+//! persisted as JSON in the app data directory so searches don't need to
+//! re-tokenize every document.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Bump when the on-disk layout changes so stale indexes get rebuilt.
+const INDEX_VERSION: u32 = 1;
+
+/// Maximum snippet length in characters.
+const SNIPPET_LEN: usize = 120;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Posting {
+    doc_id: String,
+    line: usize,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct NotesIndex {
+    version: u32,
+    /// Lines of every indexed document, used for snippets and staleness checks.
+    documents: HashMap<String, Vec<String>>,
+    /// Term -> postings. A BTreeMap so prefix lookups are a range scan.
+    terms: BTreeMap<String, Vec<Posting>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchHit {
+    pub doc_id: String,
+    /// 1-based line number within the document.
+    pub line: usize,
+    pub snippet: String,
+    pub score: f32,
+}
+
+/// Split text into lowercase alphanumeric terms.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+impl NotesIndex {
+    pub fn build<'a>(docs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut index = NotesIndex {
+            version: INDEX_VERSION,
+            ..Default::default()
+        };
+        for (id, content) in docs {
+            index.update_document(id, content);
+        }
+        index
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let index: NotesIndex = serde_json::from_str(&content).ok()?;
+        if index.version != INDEX_VERSION {
+            return None;
+        }
+        Some(index)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize notes index: {}", e))?;
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write notes index: {}", e))
+    }
+
+    /// Whether the index reflects exactly the given documents.
+    pub fn is_current<'a>(&self, docs: impl IntoIterator<Item = (&'a str, &'a str)>) -> bool {
+        let mut seen = 0;
+        for (id, content) in docs {
+            match self.documents.get(id) {
+                Some(lines) if lines.iter().map(String::as_str).eq(content.lines()) => seen += 1,
+                _ => return false,
+            }
+        }
+        seen == self.documents.len()
+    }
+
+    pub fn remove_document(&mut self, doc_id: &str) {
+        if self.documents.remove(doc_id).is_none() {
+            return;
+        }
+        self.terms.retain(|_, postings| {
+            postings.retain(|p| p.doc_id != doc_id);
+            !postings.is_empty()
+        });
+    }
+
+    pub fn update_document(&mut self, doc_id: &str, content: &str) {
+        self.remove_document(doc_id);
+
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        for (i, line) in lines.iter().enumerate() {
+            let mut counts: HashMap<String, u32> = HashMap::new();
+            for term in tokenize(line) {
+                *counts.entry(term).or_insert(0) += 1;
+            }
+            for (term, count) in counts {
+                self.terms.entry(term).or_default().push(Posting {
+                    doc_id: doc_id.to_string(),
+                    line: i + 1,
+                    count,
+                });
+            }
+        }
+        self.documents.insert(doc_id.to_string(), lines);
+    }
+
+    /// Rank matching lines across all documents.
+    ///
+    /// Every query term must match (the last one as a prefix, so results
+    /// update while typing). Lines are scored by TF-IDF, with a bonus when
+    /// the whole query appears verbatim.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query_terms = tokenize(query);
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+
+        let total_lines: usize = self.documents.values().map(Vec::len).sum();
+        let mut scores: HashMap<(&str, usize), (f32, usize)> = HashMap::new();
+
+        for (qi, term) in query_terms.iter().enumerate() {
+            let is_last = qi == query_terms.len() - 1;
+            let mut matched: HashMap<(&str, usize), f32> = HashMap::new();
+
+            for (indexed, postings) in self.terms.range(term.clone()..) {
+                if !indexed.starts_with(term.as_str()) {
+                    break;
+                }
+                if indexed != term && !is_last {
+                    continue;
+                }
+                let idf = (1.0 + total_lines as f32 / postings.len() as f32).ln();
+                for p in postings {
+                    *matched.entry((p.doc_id.as_str(), p.line)).or_insert(0.0) += p.count as f32 * idf;
+                }
+            }
+
+            for (key, score) in matched {
+                let entry = scores.entry(key).or_insert((0.0, 0));
+                entry.0 += score;
+                entry.1 += 1;
+            }
+        }
+
+        let phrase = query.trim().to_lowercase();
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .filter(|(_, (_, matched_terms))| *matched_terms == query_terms.len())
+            .filter_map(|((doc_id, line), (score, _))| {
+                let text = self.documents.get(doc_id)?.get(line - 1)?;
+                let bonus = if text.to_lowercase().contains(&phrase) { 2.0 } else { 1.0 };
+                Some(SearchHit {
+                    doc_id: doc_id.to_string(),
+                    line,
+                    snippet: snippet(text, &query_terms[0]),
+                    score: score * bonus,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.doc_id.cmp(&b.doc_id))
+                .then_with(|| a.line.cmp(&b.line))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// Cut a line down to `SNIPPET_LEN` characters centred on the first match.
+fn snippet(line: &str, term: &str) -> String {
+    let trimmed = line.trim();
+    let chars: Vec<char> = trimmed.chars().collect();
+    if chars.len() <= SNIPPET_LEN {
+        return trimmed.to_string();
+    }
+
+    let lower = trimmed.to_lowercase();
+    let match_char = lower
+        .find(term)
+        .map(|byte| lower[..byte].chars().count())
+        .unwrap_or(0)
+        .min(chars.len());
+
+    let start = match_char.saturating_sub(SNIPPET_LEN / 3);
+    let end = (start + SNIPPET_LEN).min(chars.len());
+    let start = end.saturating_sub(SNIPPET_LEN);
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    out.extend(&chars[start..end]);
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_ranks_and_reports_lines() {
+        let index = NotesIndex::build([
+            ("notes", "# Ideas\nrust tauri app\nrust rust everywhere\nnothing here"),
+            ("other", "learn tauri"),
+        ]);
+
+        let hits = index.search("rust", 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].line, 3);
+        assert_eq!(hits[1].line, 2);
+
+        let hits = index.search("tauri", 10);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().any(|h| h.doc_id == "other" && h.line == 1));
+    }
+
+    #[test]
+    fn test_search_requires_all_terms_and_prefixes_last() {
+        let index = NotesIndex::build([("notes", "alpha beta\nalpha gamma\nbetamax")]);
+
+        let hits = index.search("alpha bet", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].line, 1);
+        assert_eq!(hits[0].snippet, "alpha beta");
+
+        assert!(index.search("   ", 10).is_empty());
+        assert!(index.search("delta", 10).is_empty());
+    }
+
+    #[test]
+    fn test_update_replaces_document() {
+        let mut index = NotesIndex::build([("notes", "old text")]);
+        index.update_document("notes", "new text");
+
+        assert!(index.search("old", 10).is_empty());
+        assert_eq!(index.search("new", 10).len(), 1);
+        assert!(index.is_current([("notes", "new text")]));
+        assert!(!index.is_current([("notes", "old text")]));
+    }
+
+    #[test]
+    fn test_snippet_truncates_around_match() {
+        let line = format!("{} needle {}", "a".repeat(200), "b".repeat(200));
+        let s = snippet(&line, "needle");
+        assert!(s.contains("needle"));
+        assert!(s.starts_with('…') && s.ends_with('…'));
+    }
+}
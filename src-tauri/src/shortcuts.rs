@@ -0,0 +1,149 @@
+//! System-wide shortcuts that work while Peko is in the background.
+//!
+//! Shortcuts are stored in `AppSettings::global_shortcuts` as accelerator
+//! strings (e.g. `Super+G`) and registered through the global-shortcut
+//! plugin. `check` parses them and sorts out the ones that can't be used:
+//! invalid input is an error, while clashes with the app menu are reported
+//! as conflicts and left unregistered.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tauri_plugin_global_shortcut::Shortcut;
+
+use crate::Website;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShortcutAction {
+    /// Bring up the active tab
+    Summon,
+    ToggleNotes,
+    OpenSite { site: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GlobalShortcut {
+    pub accelerator: String,
+    pub action: ShortcutAction,
+}
+
+/// A shortcut that was saved but couldn't be registered.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ShortcutConflict {
+    pub accelerator: String,
+    pub reason: String,
+}
+
+/// Shortcuts that passed `check`, ready to register.
+pub struct Checked {
+    pub shortcuts: Vec<(Shortcut, GlobalShortcut)>,
+    pub conflicts: Vec<ShortcutConflict>,
+}
+
+pub fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(accelerator.trim())
+        .map_err(|e| format!("Invalid shortcut {}: {}", accelerator, e))
+}
+
+/// Validate shortcuts against the website list and the app menu's
+/// accelerators. Fails on unparseable accelerators, unknown sites and the
+/// same key combination used twice.
+pub fn check(shortcuts: &[GlobalShortcut], websites: &[Website], menu_accelerators: &[String]) -> Result<Checked, String> {
+    let menu: HashMap<u32, &str> = menu_accelerators
+        .iter()
+        .filter_map(|accelerator| parse(accelerator).ok().map(|s| (s.id(), accelerator.as_str())))
+        .collect();
+
+    let mut seen: HashMap<u32, &str> = HashMap::new();
+    let mut checked = Checked { shortcuts: Vec::new(), conflicts: Vec::new() };
+
+    for shortcut in shortcuts {
+        let parsed = parse(&shortcut.accelerator)?;
+        if parsed.mods.is_empty() {
+            return Err(format!("{} needs at least one modifier key", shortcut.accelerator));
+        }
+        if let ShortcutAction::OpenSite { site } = &shortcut.action {
+            if !websites.iter().any(|w| &w.id == site) {
+                return Err(format!("Unknown site for {}: {}", shortcut.accelerator, site));
+            }
+        }
+        if let Some(other) = seen.insert(parsed.id(), &shortcut.accelerator) {
+            return Err(format!("{} and {} are the same shortcut", other, shortcut.accelerator));
+        }
+
+        match menu.get(&parsed.id()) {
+            Some(menu_accelerator) => checked.conflicts.push(ShortcutConflict {
+                accelerator: shortcut.accelerator.clone(),
+                reason: format!("Already used by the Peko menu ({})", menu_accelerator),
+            }),
+            None => checked.shortcuts.push((parsed, shortcut.clone())),
+        }
+    }
+
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shortcut(accelerator: &str, action: ShortcutAction) -> GlobalShortcut {
+        GlobalShortcut { accelerator: accelerator.to_string(), action }
+    }
+
+    fn open(site: &str) -> ShortcutAction {
+        ShortcutAction::OpenSite { site: site.to_string() }
+    }
+
+    #[test]
+    fn test_action_serialization() {
+        let json = serde_json::to_string(&shortcut("Super+G", open("gemini"))).unwrap();
+        assert_eq!(json, r#"{"accelerator":"Super+G","action":{"type":"open_site","site":"gemini"}}"#);
+
+        let parsed: ShortcutAction = serde_json::from_str(r#"{"type":"toggle_notes"}"#).unwrap();
+        assert_eq!(parsed, ShortcutAction::ToggleNotes);
+    }
+
+    #[test]
+    fn test_check_accepts_valid_shortcuts() {
+        let websites = crate::AppSettings::default().websites;
+        let checked = check(
+            &[shortcut("Super+G", open("gemini")), shortcut("Ctrl+Alt+Space", ShortcutAction::Summon)],
+            &websites,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(checked.shortcuts.len(), 2);
+        assert!(checked.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_check_rejects_invalid_shortcuts() {
+        let websites = crate::AppSettings::default().websites;
+        let summon = |accelerator: &str| shortcut(accelerator, ShortcutAction::Summon);
+
+        assert!(check(&[summon("Super+NotAKey")], &websites, &[]).is_err());
+        assert!(check(&[summon("G")], &websites, &[]).is_err());
+        assert!(check(&[shortcut("Super+G", open("missing"))], &websites, &[]).is_err());
+        // Same keys written differently
+        assert!(check(&[summon("Shift+Super+G"), shortcut("super+shift+g", ShortcutAction::ToggleNotes)], &websites, &[]).is_err());
+    }
+
+    #[test]
+    fn test_check_reports_menu_conflicts() {
+        let websites = crate::AppSettings::default().websites;
+        let menu = vec!["CmdOrCtrl+N".to_string(), "CmdOrCtrl+1".to_string()];
+        let checked = check(
+            &[shortcut("CmdOrCtrl+N", ShortcutAction::ToggleNotes), shortcut("CmdOrCtrl+Shift+N", ShortcutAction::Summon)],
+            &websites,
+            &menu,
+        )
+        .unwrap();
+
+        assert_eq!(checked.shortcuts.len(), 1);
+        assert_eq!(checked.shortcuts[0].1.accelerator, "CmdOrCtrl+Shift+N");
+        assert_eq!(checked.conflicts.len(), 1);
+        assert_eq!(checked.conflicts[0].accelerator, "CmdOrCtrl+N");
+    }
+}
@@ -0,0 +1,467 @@
+//! A profile's browsing data: clearing it by kind, measuring it and removing
+//! it with the profile.
+//!
+//! On Linux and Windows each profile has its own store in `profiles/{id}`
+//! under the app data directory, so clearing affects only the sites of that
+//! profile. WebKit on macOS ignores the data directory: profiles get a store
+//! of their own on macOS 14 and later, and share the default store before
+//! that. Clearing there is limited to the records of the profile's sites'
+//! domains, and disk usage isn't known.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of the profile stores in the app data directory.
+const PROFILES_DIR: &str = "profiles";
+
+/// Prefix of the per-site data directories used before profiles existed.
+const LEGACY_PREFIX: &str = "webview_";
+
+/// File in the app data directory listing data to delete whose files were
+/// still in use when its profile or site was removed.
+const PENDING_FILE: &str = "pending-removal.json";
+
+/// Whether profile stores are the directories below, so their size is known.
+pub const STORE_DIRS: bool = cfg!(not(target_os = "macos"));
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataKind {
+    Cookies,
+    /// HTTP and memory caches and Cache Storage
+    Cache,
+    /// Local and session storage, IndexedDB, WebSQL and service workers
+    Storage,
+    All,
+}
+
+/// Called with the outcome once the webview has cleared the data.
+pub type Done = Box<dyn FnOnce(Result<(), String>) + Send>;
+
+pub fn data_dir(app_data_dir: &Path, profile: &str) -> PathBuf {
+    app_data_dir.join(PROFILES_DIR).join(profile)
+}
+
+fn legacy_dir(app_data_dir: &Path, site: &str) -> PathBuf {
+    app_data_dir.join(format!("{}{}", LEGACY_PREFIX, site))
+}
+
+/// Move a site's data from before profiles existed into `profile`, unless
+/// the profile has data already.
+pub fn migrate(app_data_dir: &Path, site: &str, profile: &str) -> Result<(), String> {
+    let from = legacy_dir(app_data_dir, site);
+    let to = data_dir(app_data_dir, profile);
+    if !from.is_dir() || to.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(app_data_dir.join(PROFILES_DIR))
+        .and_then(|_| fs::rename(&from, &to))
+        .map_err(|e| format!("Failed to move data of {} to profile {}: {}", site, profile, e))
+}
+
+/// Total size of the files under `path`, without following links.
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn remove(path: &Path, name: &str) -> Result<(), String> {
+    match fs::remove_dir_all(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove data of {}: {}", name, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Delete a profile's data directory. No webview may be using it.
+pub fn remove_dir(app_data_dir: &Path, profile: &str) -> Result<(), String> {
+    remove(&data_dir(app_data_dir, profile), profile)
+}
+
+/// Delete the data of removed `profiles`, and of removed `sites` from
+/// before profiles existed. Only call it once settings without them are
+/// saved. What can't be deleted yet, e.g. files still in use, is recorded
+/// in the pending file for `remove_pending` on the next start.
+pub fn remove_data(app_data_dir: &Path, profiles: &[String], sites: &[String]) -> Result<(), String> {
+    let mut pending = read_pending(app_data_dir)?;
+    for profile in profiles {
+        if let Err(e) = remove_dir(app_data_dir, profile) {
+            log::warn!("{}", e);
+            pending.profiles.push(profile.clone());
+        }
+    }
+    for site in sites {
+        if let Err(e) = remove(&legacy_dir(app_data_dir, site), site) {
+            log::warn!("{}", e);
+            pending.sites.push(site.clone());
+        }
+    }
+    pending.profiles.dedup();
+    pending.sites.dedup();
+    write_pending(app_data_dir, &pending)
+}
+
+/// Delete the data `remove_data` couldn't, except that of the `sites` and
+/// `profiles` the settings use, which were added again since.
+pub fn remove_pending<'a>(
+    app_data_dir: &Path,
+    sites: impl IntoIterator<Item = &'a str>,
+    profiles: impl IntoIterator<Item = &'a str>,
+) {
+    let pending = match read_pending(app_data_dir) {
+        Ok(pending) => pending,
+        Err(e) => return log::warn!("{}", e),
+    };
+    let sites: Vec<&str> = sites.into_iter().collect();
+    let profiles: Vec<&str> = profiles.into_iter().collect();
+    let mut left = PendingRemoval::default();
+    // The file may have been edited: only names that can't leave the
+    // app data directory are deleted
+    for profile in pending.profiles {
+        if profiles.contains(&profile.as_str()) || !crate::profiles::valid_id(&profile) {
+            continue;
+        }
+        match remove_dir(app_data_dir, &profile) {
+            Ok(()) => log::info!("Removed data of deleted {}", profile),
+            Err(e) => {
+                log::warn!("{}", e);
+                left.profiles.push(profile);
+            }
+        }
+    }
+    for site in pending.sites {
+        if sites.contains(&site.as_str()) || !crate::profiles::valid_id(&site) {
+            continue;
+        }
+        match remove(&legacy_dir(app_data_dir, &site), &site) {
+            Ok(()) => log::info!("Removed data of deleted {}", site),
+            Err(e) => {
+                log::warn!("{}", e);
+                left.sites.push(site);
+            }
+        }
+    }
+    if let Err(e) = write_pending(app_data_dir, &left) {
+        log::warn!("{}", e);
+    }
+}
+
+/// Data waiting to be deleted, from the pending file.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct PendingRemoval {
+    #[serde(default)]
+    profiles: Vec<String>,
+    /// Sites whose data from before profiles existed is left
+    #[serde(default)]
+    sites: Vec<String>,
+}
+
+fn read_pending(app_data_dir: &Path) -> Result<PendingRemoval, String> {
+    match fs::read_to_string(app_data_dir.join(PENDING_FILE)) {
+        Ok(content) => {
+            serde_json::from_str(&content).map_err(|e| format!("Failed to read pending data removals: {}", e))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PendingRemoval::default()),
+        Err(e) => Err(format!("Failed to read pending data removals: {}", e)),
+    }
+}
+
+fn write_pending(app_data_dir: &Path, pending: &PendingRemoval) -> Result<(), String> {
+    let path = app_data_dir.join(PENDING_FILE);
+    if pending == &PendingRemoval::default() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to write pending data removals: {}", e))
+            }
+            _ => Ok(()),
+        };
+    }
+    let content = serde_json::to_string_pretty(pending)
+        .map_err(|e| format!("Failed to write pending data removals: {}", e))?;
+    crate::write_file_atomic(&path, content.as_bytes())
+        .map_err(|e| format!("Failed to write pending data removals: {}", e))
+}
+
+/// Whether a macOS data record, named after a registrable domain, belongs to
+/// a site on one of `hosts`.
+#[cfg(any(target_os = "macos", test))]
+fn record_matches(record: &str, hosts: &[String]) -> bool {
+    hosts.iter().any(|host| {
+        host == record || host.strip_suffix(record).is_some_and(|rest| rest.ends_with('.'))
+    })
+}
+
+/// Clear `kind` data from the store of a profile through the webview of one
+/// of its sites. `hosts` are the hosts of the profile's sites, which limit
+/// clearing to their records where the store is shared.
+pub fn clear(window: &tauri::WebviewWindow, kind: DataKind, hosts: Vec<String>, done: Done) -> Result<(), String> {
+    window
+        .with_webview(move |webview| platform::clear(webview, kind, hosts, done))
+        .map_err(|e| format!("Failed to clear site data: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{DataKind, Done};
+    use webkit2gtk::{gio, glib, WebViewExt, WebsiteDataManagerExtManual, WebsiteDataTypes};
+
+    pub fn clear(webview: tauri::webview::PlatformWebview, kind: DataKind, _hosts: Vec<String>, done: Done) {
+        let types = match kind {
+            DataKind::Cookies => WebsiteDataTypes::COOKIES,
+            DataKind::Cache => {
+                WebsiteDataTypes::DISK_CACHE
+                    | WebsiteDataTypes::MEMORY_CACHE
+                    | WebsiteDataTypes::OFFLINE_APPLICATION_CACHE
+                    | WebsiteDataTypes::DOM_CACHE
+            }
+            DataKind::Storage => {
+                WebsiteDataTypes::LOCAL_STORAGE
+                    | WebsiteDataTypes::SESSION_STORAGE
+                    | WebsiteDataTypes::INDEXEDDB_DATABASES
+                    | WebsiteDataTypes::WEBSQL_DATABASES
+                    | WebsiteDataTypes::SERVICE_WORKER_REGISTRATIONS
+            }
+            DataKind::All => WebsiteDataTypes::ALL,
+        };
+        let Some(manager) = webview.inner().website_data_manager() else {
+            return done(Err("The webview has no data manager".to_string()));
+        };
+        // A zero time span clears data of any age
+        manager.clear(types, glib::TimeSpan::from_seconds(0), None::<&gio::Cancellable>, move |result| {
+            done(result.map_err(|e| e.to_string()))
+        });
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{DataKind, Done};
+    use webview2_com::ClearBrowsingDataCompletedHandler;
+    use webview2_com::Microsoft::Web::WebView2::Win32::*;
+    use windows_core::Interface;
+
+    pub fn clear(webview: tauri::webview::PlatformWebview, kind: DataKind, _hosts: Vec<String>, done: Done) {
+        let kinds = match kind {
+            DataKind::Cookies => COREWEBVIEW2_BROWSING_DATA_KINDS_COOKIES,
+            DataKind::Cache => COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE | COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE,
+            DataKind::Storage => {
+                COREWEBVIEW2_BROWSING_DATA_KINDS_LOCAL_STORAGE
+                    | COREWEBVIEW2_BROWSING_DATA_KINDS_INDEXED_DB
+                    | COREWEBVIEW2_BROWSING_DATA_KINDS_WEB_SQL
+                    | COREWEBVIEW2_BROWSING_DATA_KINDS_FILE_SYSTEMS
+                    | COREWEBVIEW2_BROWSING_DATA_KINDS_SERVICE_WORKERS
+            }
+            DataKind::All => COREWEBVIEW2_BROWSING_DATA_KINDS_ALL_PROFILE,
+        };
+        let profile = unsafe {
+            webview
+                .controller()
+                .CoreWebView2()
+                .and_then(|core| core.cast::<ICoreWebView2_13>())
+                .and_then(|core| core.Profile())
+                .and_then(|profile| profile.cast::<ICoreWebView2Profile2>())
+        };
+        let profile = match profile {
+            Ok(profile) => profile,
+            Err(e) => return done(Err(e.to_string())),
+        };
+        let handler = ClearBrowsingDataCompletedHandler::create(Box::new(move |result| {
+            done(result.map_err(|e| e.to_string()));
+            Ok(())
+        }));
+        // On failure the handler is never called, and `done` is dropped
+        if let Err(e) = unsafe { profile.ClearBrowsingData(kinds, &handler) } {
+            log::warn!("Failed to clear site data: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{record_matches, DataKind, Done};
+    use block2::RcBlock;
+    use objc2::{rc::Retained, MainThreadMarker};
+    use objc2_foundation::{NSArray, NSSet, NSString};
+    use objc2_web_kit::*;
+    use std::cell::Cell;
+    use std::ptr::NonNull;
+
+    pub fn clear(webview: tauri::webview::PlatformWebview, kind: DataKind, hosts: Vec<String>, done: Done) {
+        // `with_webview` runs on the main thread
+        let mtm = MainThreadMarker::new().expect("not on the main thread");
+        unsafe {
+            let types: Retained<NSSet<NSString>> = match kind {
+                DataKind::Cookies => NSSet::from_slice(&[WKWebsiteDataTypeCookies]),
+                DataKind::Cache => NSSet::from_slice(&[
+                    WKWebsiteDataTypeDiskCache,
+                    WKWebsiteDataTypeMemoryCache,
+                    WKWebsiteDataTypeOfflineWebApplicationCache,
+                    WKWebsiteDataTypeFetchCache,
+                ]),
+                DataKind::Storage => NSSet::from_slice(&[
+                    WKWebsiteDataTypeLocalStorage,
+                    WKWebsiteDataTypeSessionStorage,
+                    WKWebsiteDataTypeIndexedDBDatabases,
+                    WKWebsiteDataTypeWebSQLDatabases,
+                    WKWebsiteDataTypeServiceWorkerRegistrations,
+                ]),
+                DataKind::All => WKWebsiteDataStore::allWebsiteDataTypes(mtm),
+            };
+            let webview: &WKWebView = &*webview.inner().cast();
+            let store = webview.configuration().websiteDataStore();
+
+            // The store may be shared with other profiles: remove only the records
+            // of this profile's sites
+            let done = Cell::new(Some(done));
+            let remove_types = types.clone();
+            let remove_from = store.clone();
+            let on_records = RcBlock::new(move |records: NonNull<NSArray<WKWebsiteDataRecord>>| {
+                let records: Vec<_> = records
+                    .as_ref()
+                    .iter()
+                    .filter(|record| record_matches(&record.displayName().to_string(), &hosts))
+                    .collect();
+                let done = Cell::new(done.take());
+                let on_removed = RcBlock::new(move || {
+                    if let Some(done) = done.take() {
+                        done(Ok(()));
+                    }
+                });
+                remove_from.removeDataOfTypes_forDataRecords_completionHandler(
+                    &remove_types,
+                    &NSArray::from_retained_slice(&records),
+                    &on_removed,
+                );
+            });
+            store.fetchDataRecordsOfTypes_completionHandler(&types, &on_records);
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use super::{DataKind, Done};
+
+    pub fn clear(_webview: tauri::webview::PlatformWebview, _kind: DataKind, _hosts: Vec<String>, done: Done) {
+        done(Err("Clearing site data is not supported on this platform".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    /// Legacy data of 120 bytes for each site, plus `settings.json`.
+    fn app_data_dir(name: &str, sites: &[&str]) -> TempDir {
+        let dir = TempDir::new(name);
+        for site in sites {
+            let data = legacy_dir(&dir, site).join("storage");
+            fs::create_dir_all(&data).unwrap();
+            fs::write(data.join("a"), [0u8; 100]).unwrap();
+            fs::write(legacy_dir(&dir, site).join("b"), [0u8; 20]).unwrap();
+        }
+        fs::write(dir.join("settings.json"), "{}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_migrate_and_size() {
+        let dir = app_data_dir("sitedata-migrate", &["gemini", "work"]);
+        fs::create_dir_all(data_dir(&dir, "work")).unwrap();
+
+        // Legacy data moves into the site's profile, unless that has data
+        assert!(migrate(&dir, "gemini", "google").is_ok());
+        assert!(!legacy_dir(&dir, "gemini").exists());
+        assert_eq!(dir_size(&data_dir(&dir, "google")), 120);
+        assert_eq!(dir_size(&data_dir(&dir, "missing")), 0);
+        assert!(migrate(&dir, "work", "work").is_ok());
+        assert!(legacy_dir(&dir, "work").exists());
+        assert_eq!(dir_size(&data_dir(&dir, "work")), 0);
+        assert!(migrate(&dir, "missing", "missing").is_ok());
+        assert!(!data_dir(&dir, "missing").exists());
+
+        assert!(remove_dir(&dir, "google").is_ok());
+        assert!(!data_dir(&dir, "google").exists());
+        assert!(remove_dir(&dir, "google").is_ok());
+    }
+
+    #[test]
+    fn test_remove_only_recorded_data() {
+        let dir = app_data_dir("sitedata-remove", &["removed"]);
+        fs::create_dir_all(data_dir(&dir, "deleted")).unwrap();
+
+        // Only data recorded as removed is deleted, never what the loaded
+        // settings happen not to mention
+        assert!(remove_data(&dir, &[], &["removed".to_string()]).is_ok());
+        assert!(!legacy_dir(&dir, "removed").exists());
+        assert!(data_dir(&dir, "deleted").exists());
+        remove_pending(&dir, [], []);
+        assert!(data_dir(&dir, "deleted").exists());
+        assert!(!dir.join(PENDING_FILE).exists());
+    }
+
+    #[test]
+    fn test_pending_removal_retried() {
+        let dir = app_data_dir("sitedata-pending", &["work"]);
+        for profile in ["deleted", "google", "work"] {
+            fs::create_dir_all(data_dir(&dir, profile)).unwrap();
+        }
+
+        // Removals that fail are retried on the next start, unless the
+        // profile is in use again
+        let pending = PendingRemoval {
+            profiles: vec!["deleted".to_string(), "work".to_string()],
+            sites: Vec::new(),
+        };
+        assert!(write_pending(&dir, &pending).is_ok());
+        assert_eq!(read_pending(&dir), Ok(pending));
+        remove_pending(&dir, ["gemini", "work"], ["google", "work"]);
+        assert!(!data_dir(&dir, "deleted").exists());
+        assert!(data_dir(&dir, "google").exists());
+        assert!(data_dir(&dir, "work").exists());
+        assert!(legacy_dir(&dir, "work").exists());
+        assert!(!dir.join(PENDING_FILE).exists());
+        assert!(dir.join("settings.json").exists());
+
+        // A removal list that can't be read stops any further removal
+        fs::write(dir.join(PENDING_FILE), "{").unwrap();
+        assert!(remove_data(&dir, &["google".to_string()], &[]).is_err());
+        remove_pending(&dir, [], []);
+        assert!(data_dir(&dir, "google").exists());
+    }
+
+    #[test]
+    fn test_pending_removal_rejects_paths() {
+        let dir = app_data_dir("sitedata-paths", &[]);
+        let pending = PendingRemoval {
+            profiles: vec!["..".to_string()],
+            sites: vec!["../..".to_string()],
+        };
+        assert!(write_pending(&dir, &pending).is_ok());
+        remove_pending(&dir, [], []);
+        assert!(dir.exists());
+        assert!(dir.join("settings.json").exists());
+        assert!(!dir.join(PENDING_FILE).exists());
+    }
+
+    #[test]
+    fn test_record_matches() {
+        let hosts = ["gemini.google.com".to_string(), "chatgpt.com".to_string()];
+        assert!(record_matches("google.com", &hosts));
+        assert!(record_matches("chatgpt.com", &hosts));
+        assert!(!record_matches("notgoogle.com", &hosts));
+        assert!(!record_matches("openai.com", &hosts));
+    }
+}
@@ -0,0 +1,206 @@
+//! Snippet library: saved prompts and text blocks that can be typed into
+//! the active tab from the Snippets menu, the command palette or their own
+//! shortcut.
+//!
+//! A body may contain placeholders, filled in when the snippet is inserted:
+//! `{{clipboard}}` (the clipboard's text), `{{selection}}` (the text
+//! selected in the page) and `{{date}}` (today, as YYYY-MM-DD). Anything
+//! else in double braces is left as written.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::insert::Piece;
+use crate::{shortcuts, Website};
+
+/// Menu item ids of snippets are this prefix followed by the snippet id.
+pub const MENU_PREFIX: &str = "snippet:";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    /// Site ids the snippet is meant for. Empty means any site.
+    #[serde(default)]
+    pub sites: Vec<String>,
+    /// Menu accelerator that inserts the snippet
+    #[serde(default)]
+    pub accelerator: Option<String>,
+}
+
+impl Snippet {
+    pub fn targets(&self, site: &str) -> bool {
+        self.sites.is_empty() || self.sites.iter().any(|s| s == site)
+    }
+
+    pub fn menu_id(&self) -> String {
+        format!("{}{}", MENU_PREFIX, self.id)
+    }
+
+    pub fn uses(&self, placeholder: &str) -> bool {
+        uses(&self.body, placeholder)
+    }
+}
+
+/// Whether `body` contains `{{placeholder}}`.
+pub fn uses(body: &str, placeholder: &str) -> bool {
+    placeholders(body).any(|(_, name)| name == placeholder)
+}
+
+/// `(range, name)` of every `{{name}}` in `body`.
+fn placeholders(body: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let start = pos + body[pos..].find("{{")?;
+        let end = start + body[start..].find("}}")? + 2;
+        pos = end;
+        Some((start..end, body[start + 2..end - 2].trim()))
+    })
+}
+
+/// Split `body` into the pieces to insert. `fill` supplies the value of a
+/// placeholder, or `None` to leave it as written; an unfilled
+/// `{{selection}}` is resolved by the page.
+pub fn expand(body: &str, fill: impl Fn(&str) -> Option<String>) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut pos = 0;
+
+    for (range, name) in placeholders(body) {
+        text.push_str(&body[pos..range.start]);
+        pos = range.end;
+        match fill(name) {
+            Some(value) => text.push_str(&value),
+            None if name == "selection" => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Selection);
+            }
+            None => text.push_str(&body[range]),
+        }
+    }
+    text.push_str(&body[pos..]);
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    pieces
+}
+
+/// Check a snippet list before saving it: ids must be unique and usable in
+/// menu ids, sites must exist, and accelerators must be valid and not
+/// already taken by the menu.
+pub fn validate(snippets: &[Snippet], websites: &[Website], menu_accelerators: &[String]) -> Result<(), String> {
+    let mut ids: Vec<&str> = Vec::new();
+    for snippet in snippets {
+        if snippet.id.is_empty() || !snippet.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("Invalid snippet id: {:?}", snippet.id));
+        }
+        if ids.contains(&snippet.id.as_str()) {
+            return Err(format!("Duplicate snippet id: {}", snippet.id));
+        }
+        ids.push(&snippet.id);
+
+        if snippet.name.trim().is_empty() {
+            return Err("Snippet name must not be empty".to_string());
+        }
+        if let Some(site) = snippet.sites.iter().find(|site| !websites.iter().any(|w| &w.id == *site)) {
+            return Err(format!("Unknown site for snippet {}: {}", snippet.name, site));
+        }
+    }
+    check_accelerators(snippets, menu_accelerators)
+}
+
+/// Snippet accelerators must parse and must not clash with each other or
+/// with `menu_accelerators`.
+pub fn check_accelerators(snippets: &[Snippet], menu_accelerators: &[String]) -> Result<(), String> {
+    let mut seen: HashMap<u32, String> = HashMap::new();
+    for accelerator in menu_accelerators {
+        seen.insert(shortcuts::parse(accelerator)?.id(), format!("the menu ({})", accelerator));
+    }
+
+    for snippet in snippets {
+        let Some(accelerator) = snippet.accelerator.as_deref() else {
+            continue;
+        };
+        let shortcut = shortcuts::parse(accelerator)?;
+        if shortcut.mods.is_empty() {
+            return Err(format!("{} needs at least one modifier key", accelerator));
+        }
+        if let Some(other) = seen.insert(shortcut.id(), format!("snippet {}", snippet.name)) {
+            return Err(format!("{} for snippet {} is already used by {}", accelerator, snippet.name, other));
+        }
+    }
+    Ok(())
+}
+
+/// Accelerators bound to snippets.
+pub fn accelerators(snippets: &[Snippet]) -> Vec<String> {
+    snippets.iter().filter_map(|snippet| snippet.accelerator.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(id: &str, accelerator: Option<&str>) -> Snippet {
+        Snippet {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            body: "Explain {{selection}}".to_string(),
+            sites: Vec::new(),
+            accelerator: accelerator.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_expand_fills_placeholders() {
+        let fill = |name: &str| match name {
+            "clipboard" => Some("copied".to_string()),
+            "date" => Some("2024-01-15".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            expand("On {{ date }}: {{selection}}\n\n{{clipboard}} {{unknown}} {{", fill),
+            [
+                Piece::Text("On 2024-01-15: ".to_string()),
+                Piece::Selection,
+                Piece::Text("\n\ncopied {{unknown}} {{".to_string()),
+            ]
+        );
+        assert_eq!(expand("{{selection}}", fill), [Piece::Selection]);
+        assert!(expand("", fill).is_empty());
+
+        let sent = |name: &str| (name == "selection").then(|| "passage".to_string());
+        assert_eq!(expand("About this: {{selection}}", sent), [Piece::Text("About this: passage".to_string())]);
+
+        let snippet = snippet("a", None);
+        assert!(snippet.uses("selection"));
+        assert!(!snippet.uses("clipboard"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let websites = crate::AppSettings::default().websites;
+        let menu = vec!["CmdOrCtrl+N".to_string()];
+        assert!(validate(&[snippet("a", Some("CmdOrCtrl+Alt+1")), snippet("b", None)], &websites, &menu).is_ok());
+
+        assert!(validate(&[snippet("a", None), snippet("a", None)], &websites, &menu).is_err());
+        assert!(validate(&[snippet("a:b", None)], &websites, &menu).is_err());
+        assert!(validate(&[snippet("a", Some("K"))], &websites, &menu).is_err());
+        assert!(validate(&[snippet("a", Some("CmdOrCtrl+Banana"))], &websites, &menu).is_err());
+
+        let error = validate(&[snippet("a", Some("cmdorctrl+n"))], &websites, &menu).unwrap_err();
+        assert!(error.contains("menu"), "{}", error);
+        let twice = [snippet("a", Some("CmdOrCtrl+Alt+1")), snippet("b", Some("CmdOrCtrl+Alt+1"))];
+        assert!(validate(&twice, &websites, &menu).is_err());
+
+        let mut targeted = snippet("a", None);
+        targeted.sites = vec!["gemini".to_string()];
+        assert!(validate(std::slice::from_ref(&targeted), &websites, &menu).is_ok());
+        assert!(targeted.targets("gemini") && !targeted.targets("notebooklm"));
+        targeted.sites.push("missing".to_string());
+        assert!(validate(&[targeted], &websites, &menu).is_err());
+    }
+}
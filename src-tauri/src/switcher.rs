@@ -0,0 +1,157 @@
+//! Tab switcher data and overlay placement.
+//!
+//! Positions are physical pixels in desktop coordinates, which span all
+//! monitors. Overlay sizes are configured in logical pixels and scaled by
+//! the monitor the overlay ends up on.
+
+use serde::Serialize;
+
+use crate::dock::Rect;
+use crate::history::TabHistory;
+use crate::Website;
+
+/// Logical size of one tab card in the switcher, plus the overlay padding.
+const CARD_WIDTH: f64 = 112.0;
+const OVERLAY_PADDING: f64 = 24.0;
+const OVERLAY_HEIGHT: f64 = 150.0;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SwitcherTab {
+    pub id: String,
+    pub name: String,
+    pub emoji: String,
+    /// Title of the page the site window is showing
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub favicon: Option<String>,
+    /// Milliseconds since the Unix epoch; `None` if not shown this session
+    pub last_active: Option<i64>,
+    pub active: bool,
+}
+
+/// The current page of a site window: (url, title).
+pub type PageInfo = (Option<String>, Option<String>);
+
+/// Tabs in most-recently-used order.
+pub fn tabs(
+    websites: &[Website],
+    active_tab: &str,
+    history: &TabHistory,
+    page: impl Fn(&str) -> PageInfo,
+) -> Vec<SwitcherTab> {
+    history
+        .order(websites)
+        .into_iter()
+        .map(|website| {
+            let (url, title) = page(&website.id);
+            let favicon = favicon_url(url.as_deref().unwrap_or(&website.url));
+            SwitcherTab {
+                id: website.id.clone(),
+                name: website.name.clone(),
+                emoji: website.emoji.clone(),
+                title,
+                url,
+                favicon,
+                last_active: history.last_active(&website.id),
+                active: website.id == active_tab,
+            }
+        })
+        .collect()
+}
+
+/// `/favicon.ico` on the page's origin. Pages that declare a different icon
+/// fall back to the site's emoji in the switcher.
+pub fn favicon_url(page_url: &str) -> Option<String> {
+    let url = url::Url::parse(page_url).ok()?;
+    if url.scheme() != "https" && url.scheme() != "http" {
+        return None;
+    }
+    url.join("/favicon.ico").ok().map(String::from)
+}
+
+/// Logical (width, height) of the switcher for `tab_count` tabs.
+pub fn overlay_size(tab_count: usize) -> (f64, f64) {
+    (tab_count.max(1) as f64 * CARD_WIDTH + OVERLAY_PADDING, OVERLAY_HEIGHT)
+}
+
+/// Top-left corner for an overlay of `size` (physical), centered
+/// horizontally over `anchor` and `top_margin` below its top edge, then
+/// moved inside `work_area` so no part ends up off screen.
+pub fn overlay_position(anchor: Rect, work_area: Rect, size: (u32, u32), top_margin: i32) -> (i32, i32) {
+    let x = anchor.x + (anchor.width as i32 - size.0 as i32) / 2;
+    let y = anchor.y + top_margin;
+
+    let clamp = |value: i32, start: i32, length: u32, extent: u32| {
+        // An overlay larger than the work area sticks to its top/left edge
+        let max = start + (length as i32 - extent as i32).max(0);
+        value.clamp(start, max)
+    };
+    (
+        clamp(x, work_area.x, work_area.width, size.0),
+        clamp(y, work_area.y, work_area.height, size.1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY: Rect = Rect { x: 0, y: 25, width: 1920, height: 1055 };
+    // A second monitor to the left of the primary one
+    const LEFT: Rect = Rect { x: -2560, y: 0, width: 2560, height: 1440 };
+
+    #[test]
+    fn test_overlay_centered_over_window() {
+        let window = Rect { x: 200, y: 100, width: 1200, height: 800 };
+        assert_eq!(overlay_position(window, PRIMARY, (500, 150), 20), (550, 120));
+
+        // Negative coordinates on a monitor left of the primary one
+        let window = Rect { x: -2400, y: 200, width: 1000, height: 700 };
+        assert_eq!(overlay_position(window, LEFT, (500, 150), 40), (-2150, 240));
+    }
+
+    #[test]
+    fn test_overlay_kept_on_screen() {
+        // Window hanging off the left edge of its monitor
+        let window = Rect { x: -300, y: 0, width: 400, height: 300 };
+        assert_eq!(overlay_position(window, PRIMARY, (500, 150), 20), (0, 25));
+
+        // Window near the bottom right corner
+        let window = Rect { x: 1800, y: 1000, width: 400, height: 300 };
+        assert_eq!(overlay_position(window, PRIMARY, (500, 150), 20), (1420, 930));
+
+        // Overlay bigger than the work area
+        let small = Rect { x: 0, y: 0, width: 300, height: 100 };
+        assert_eq!(overlay_position(small, small, (500, 150), 20), (0, 0));
+    }
+
+    #[test]
+    fn test_tabs_in_mru_order_with_page_info() {
+        let websites = crate::AppSettings::default().websites;
+        let mut history = TabHistory::default();
+        history.record("gemini", 10);
+        history.record("notebooklm", 20);
+
+        let tabs = tabs(&websites, "notebooklm", &history, |id| match id {
+            "gemini" => (Some("https://gemini.google.com/app/abc".to_string()), Some("Chat".to_string())),
+            _ => (None, None),
+        });
+
+        assert_eq!(tabs[0].id, "notebooklm");
+        assert!(tabs[0].active);
+        assert_eq!(tabs[0].last_active, Some(20));
+        assert_eq!(tabs[0].favicon.as_deref(), Some("https://notebooklm.google.com/favicon.ico"));
+
+        assert_eq!(tabs[1].id, "gemini");
+        assert!(!tabs[1].active);
+        assert_eq!(tabs[1].title.as_deref(), Some("Chat"));
+        assert_eq!(tabs[1].favicon.as_deref(), Some("https://gemini.google.com/favicon.ico"));
+    }
+
+    #[test]
+    fn test_favicon_url() {
+        assert_eq!(favicon_url("http://example.com:8080/a/b?c"), Some("http://example.com:8080/favicon.ico".to_string()));
+        assert_eq!(favicon_url("about:blank"), None);
+        assert_eq!(favicon_url("not a url"), None);
+    }
+}
@@ -0,0 +1,122 @@
+//! System tray icon.
+//!
+//! The tray menu reuses the app menu's item ids (site ids, `toggle_notes`,
+//! `settings`), so `handle_menu_event` handles clicks from both. Left-click
+//! shows or hides the active tab.
+
+use tauri::menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager};
+
+use crate::AppSettings;
+
+const TRAY_ID: &str = "peko";
+
+/// Id of the tray's Quit item. The app menu uses the predefined item, which
+/// isn't available in tray menus on every platform.
+pub const QUIT_ID: &str = "tray_quit";
+
+pub fn create(app: &AppHandle) -> Result<(), String> {
+    let menu = {
+        let state = app.state::<crate::SettingsState>();
+        let settings = state.0.lock().unwrap();
+        build_menu(app, &settings)?
+    };
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Peko")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                toggle_active_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+
+    builder.build(app).map_err(|e| format!("Failed to create tray icon: {}", e))?;
+    Ok(())
+}
+
+/// Rebuild the tray menu after the website list changed.
+pub fn rebuild_menu(app: &AppHandle) -> Result<(), String> {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+    let menu = {
+        let state = app.state::<crate::SettingsState>();
+        let settings = state.0.lock().unwrap();
+        build_menu(app, &settings)?
+    };
+    tray.set_menu(Some(menu)).map_err(|e| e.to_string())
+}
+
+fn build_menu(app: &AppHandle, settings: &AppSettings) -> Result<Menu<tauri::Wry>, String> {
+    // Same entries as the Tabs submenu, without the accelerators, which
+    // only apply while Peko is focused
+    let mut tabs_items: Vec<MenuItem<tauri::Wry>> = Vec::new();
+    for website in &settings.websites {
+        tabs_items.push(MenuItem::with_id(
+            app,
+            &website.id,
+            format!("{} {}", website.emoji, website.name),
+            true,
+            None::<&str>
+        ).map_err(|e| e.to_string())?);
+    }
+    let tabs_submenu = Submenu::with_items(
+        app,
+        "Tabs",
+        true,
+        &tabs_items.iter().map(|i| i as &dyn IsMenuItem<tauri::Wry>).collect::<Vec<_>>()
+    ).map_err(|e| e.to_string())?;
+
+    let notes_item = MenuItem::with_id(app, "toggle_notes", "Toggle Notes", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let settings_item = MenuItem::with_id(app, "settings", "Settings...", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let separator = PredefinedMenuItem::separator(app).map_err(|e| e.to_string())?;
+    let quit = MenuItem::with_id(app, QUIT_ID, "Quit Peko", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+
+    Menu::with_items(
+        app,
+        &[
+            &tabs_submenu as &dyn IsMenuItem<tauri::Wry>,
+            &notes_item,
+            &settings_item,
+            &separator,
+            &quit,
+        ]
+    ).map_err(|e| e.to_string())
+}
+
+/// Hide the active tab if it is showing, otherwise bring it up. Focus isn't
+/// checked: clicking the tray takes focus away from the window on Windows.
+fn toggle_active_window(app: &AppHandle) {
+    if crate::locked_out(app) {
+        return;
+    }
+    let active_tab = app.state::<crate::SettingsState>().0.lock().unwrap().active_tab.clone();
+    let Some(window) = app.get_webview_window(&active_tab) else {
+        return;
+    };
+
+    let visible = window.is_visible().unwrap_or(false) && !window.is_minimized().unwrap_or(false);
+    if visible {
+        let _ = window.hide();
+        crate::follow_site_window(app, &active_tab);
+    } else {
+        let _ = window.unminimize();
+        if let Err(e) = crate::switch_tab(app.clone(), active_tab) {
+            log::warn!("Failed to show active tab: {}", e);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Checks the capabilities in `capabilities/` against the real ACL: site
+//! windows, labelled by website id, must not reach Peko's commands.
+//!
+//! The commands here are stand-ins with the same names, since the real ones
+//! are bound to the Wry runtime. The ACL is checked before a command runs.
+
+use tauri::ipc::{CallbackFn, InvokeBody};
+use tauri::test::{get_ipc_response, mock_builder, MockRuntime, INVOKE_KEY};
+use tauri::webview::InvokeRequest;
+use tauri::{App, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+#[tauri::command]
+fn get_settings() {}
+
+#[tauri::command]
+fn save_websites() {}
+
+#[tauri::command]
+fn save_notes() {}
+
+#[tauri::command]
+fn save_keymap() {}
+
+#[tauri::command]
+fn clip_to_notes() {}
+
+#[tauri::command]
+fn unlock() {}
+
+fn app() -> App<MockRuntime> {
+    mock_builder()
+        .invoke_handler(tauri::generate_handler![
+            get_settings,
+            save_websites,
+            save_notes,
+            save_keymap,
+            clip_to_notes,
+            unlock,
+        ])
+        .build(tauri::generate_context!())
+        .unwrap()
+}
+
+fn open_window(app: &App<MockRuntime>, label: &str) -> WebviewWindow<MockRuntime> {
+    WebviewWindowBuilder::new(app, label, WebviewUrl::default()).build().unwrap()
+}
+
+fn invoke(window: &WebviewWindow<MockRuntime>, cmd: &str, url: &str) -> Result<(), String> {
+    get_ipc_response(
+        window,
+        InvokeRequest {
+            cmd: cmd.to_string(),
+            callback: CallbackFn(0),
+            error: CallbackFn(1),
+            url: url.parse().unwrap(),
+            body: InvokeBody::default(),
+            headers: Default::default(),
+            invoke_key: INVOKE_KEY.to_string(),
+        },
+    )
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Tests build without `custom-protocol`, so the app is served from `devUrl`.
+const LOCAL: &str = "http://localhost:1420/";
+
+#[test]
+fn test_own_windows_can_invoke_commands() {
+    let app = app();
+    for label in ["settings", "notes", "tab_switcher", "command_palette", "clipboard_history"] {
+        let window = open_window(&app, label);
+        for cmd in ["get_settings", "save_websites", "save_notes", "save_keymap"] {
+            assert_eq!(invoke(&window, cmd, LOCAL), Ok(()), "{} from {}", cmd, label);
+        }
+    }
+}
+
+#[test]
+fn test_site_windows_are_denied() {
+    let app = app();
+    let window = open_window(&app, "gemini");
+    for cmd in ["get_settings", "save_websites", "save_notes", "save_keymap"] {
+        assert!(invoke(&window, cmd, "https://gemini.google.com/app").is_err(), "{} allowed", cmd);
+        assert!(invoke(&window, cmd, LOCAL).is_err(), "{} allowed", cmd);
+    }
+}
+
+#[test]
+fn test_site_windows_can_send_clips() {
+    let app = app();
+    let window = open_window(&app, "gemini");
+    assert_eq!(invoke(&window, "clip_to_notes", "https://gemini.google.com/app"), Ok(()));
+
+    // Remote pages can't reach the app commands even in an app window
+    let settings = open_window(&app, "settings");
+    assert!(invoke(&settings, "save_websites", "https://evil.example/").is_err());
+}
+
+#[test]
+fn test_lock_window_can_only_unlock() {
+    let app = app();
+    let window = open_window(&app, "lock");
+    assert_eq!(invoke(&window, "unlock", LOCAL), Ok(()));
+    for cmd in ["get_settings", "save_websites", "save_notes", "save_keymap"] {
+        assert!(invoke(&window, cmd, LOCAL).is_err(), "{} allowed", cmd);
+    }
+
+    let site = open_window(&app, "gemini");
+    assert!(invoke(&site, "unlock", "https://gemini.google.com/app").is_err());
+}
@@ -0,0 +1,126 @@
+//! Drives the automation API over a real socket with the bundled client.
+#![cfg(unix)]
+
+use peko_lib::automation::{self, Backend, Client, Method, APP_ERROR, METHOD_NOT_FOUND, UNAUTHORIZED};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Stands in for the app: two sites and a notes buffer.
+#[derive(Default)]
+struct FakeApp {
+    active_tab: Mutex<String>,
+    notes: Mutex<String>,
+}
+
+impl Backend for FakeApp {
+    fn call(&self, method: Method) -> Result<Value, String> {
+        match method {
+            Method::ListSites => Ok(json!({ "active_tab": *self.active_tab.lock().unwrap() })),
+            Method::SwitchTab { tab_id } => {
+                if tab_id != "gemini" && tab_id != "notebooklm" {
+                    return Err(format!("Unknown site: {}", tab_id));
+                }
+                *self.active_tab.lock().unwrap() = tab_id;
+                Ok(Value::Null)
+            }
+            Method::GetCurrentPage { site } => Ok(json!({
+                "site": site.unwrap_or_else(|| self.active_tab.lock().unwrap().clone()),
+                "url": "https://gemini.google.com/app",
+                "title": "Gemini",
+            })),
+            Method::GetNotes => Ok(Value::String(self.notes.lock().unwrap().clone())),
+            Method::AppendNotes { text } => {
+                self.notes.lock().unwrap().push_str(&text);
+                Ok(Value::Null)
+            }
+            Method::OpenUrl { .. } => Ok(Value::Null),
+        }
+    }
+}
+
+struct Server {
+    dir: PathBuf,
+    token: String,
+}
+
+impl Server {
+    fn start(name: &str) -> Server {
+        let dir = std::env::temp_dir().join(format!("peko-automation-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let token = automation::load_or_create_token(&dir).unwrap();
+        let listener = std::os::unix::net::UnixListener::bind(automation::socket_path(&dir)).unwrap();
+        automation::serve(listener, token.clone(), Arc::new(FakeApp::default()));
+        Server { dir, token }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn test_client_round_trip() {
+    let server = Server::start("round-trip");
+    let mut client = Client::connect_to_app(&server.dir).unwrap();
+
+    client.call("switch_tab", json!({ "tab_id": "notebooklm" })).unwrap();
+    assert_eq!(client.call("list_sites", Value::Null).unwrap()["active_tab"], "notebooklm");
+
+    let page = client.call("get_current_page", json!({})).unwrap();
+    assert_eq!(page["site"], "notebooklm");
+    assert_eq!(page["title"], "Gemini");
+
+    client.call("append_notes", json!({ "text": "from a script" })).unwrap();
+    assert_eq!(client.call("get_notes", Value::Null).unwrap(), "from a script");
+
+    let error = client.call("switch_tab", json!({ "tab_id": "missing" })).unwrap_err();
+    assert_eq!(error.code, APP_ERROR);
+    assert_eq!(client.call("eval", json!({ "script": "1" })).unwrap_err().code, METHOD_NOT_FOUND);
+
+    // The connection stays usable after errors
+    assert_eq!(client.call("list_sites", Value::Null).unwrap()["active_tab"], "notebooklm");
+}
+
+#[test]
+fn test_wrong_token_is_rejected() {
+    let server = Server::start("wrong-token");
+    let socket = automation::socket_path(&server.dir);
+
+    let error = Client::connect(&socket, "not-the-token").err().unwrap();
+    assert_eq!(error.code, UNAUTHORIZED);
+    assert!(Client::connect(&socket, &server.token).is_ok());
+}
+
+#[test]
+fn test_unauthenticated_connection_is_closed() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let server = Server::start("unauthenticated");
+    let mut stream = std::os::unix::net::UnixStream::connect(automation::socket_path(&server.dir)).unwrap();
+    stream.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"get_notes\"}\n").unwrap();
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let response: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(response["error"]["code"], UNAUTHORIZED);
+
+    line.clear();
+    assert_eq!(reader.read_line(&mut line).unwrap(), 0, "server should hang up");
+}
+
+#[test]
+fn test_token_file_is_private_and_stable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = Server::start("token");
+    let mode = std::fs::metadata(automation::token_path(&server.dir)).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(automation::load_or_create_token(&server.dir).unwrap(), server.token);
+    assert_eq!(server.token.len(), 32);
+}
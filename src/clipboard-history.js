@@ -0,0 +1,187 @@
+// Clipboard History picker
+
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+let entries = []; // null while clipboard history is off
+let shown = [];
+let selected = 0;
+
+document.addEventListener('DOMContentLoaded', init);
+
+async function init() {
+    const filter = document.getElementById('history-filter');
+
+    filter.addEventListener('input', () => {
+        selected = 0;
+        render();
+    });
+    filter.addEventListener('keydown', (e) => {
+        const mod = e.metaKey || e.ctrlKey;
+        if (e.key === 'ArrowDown') {
+            e.preventDefault();
+            select(selected + 1);
+        } else if (e.key === 'ArrowUp') {
+            e.preventDefault();
+            select(selected - 1);
+        } else if (e.key === 'Enter') {
+            e.preventDefault();
+            if (shown[selected]) paste(shown[selected]);
+        } else if (mod && e.key.toLowerCase() === 'p') {
+            e.preventDefault();
+            if (shown[selected]) pin(shown[selected]);
+        } else if (mod && e.key === 'Backspace') {
+            e.preventDefault();
+            if (shown[selected]) remove(shown[selected]);
+        } else if (e.key === 'Escape') {
+            e.preventDefault();
+            hide();
+        }
+    });
+
+    document.getElementById('clear-btn').addEventListener('click', async () => {
+        try {
+            await invoke('clear_clipboard_history');
+        } catch (error) {
+            console.error('Failed to clear clipboard history:', error);
+        }
+        filter.focus();
+    });
+
+    // Hide when the user clicks elsewhere
+    window.addEventListener('blur', hide);
+
+    // The window is reused; start over each time it is shown
+    await listen('clipboard-history-shown', () => {
+        filter.value = '';
+        selected = 0;
+        load();
+        filter.focus();
+    });
+    await listen('clipboard-history-updated', load);
+
+    load();
+    filter.focus();
+}
+
+async function load() {
+    try {
+        entries = await invoke('get_clipboard_history');
+    } catch (error) {
+        console.error('Failed to load clipboard history:', error);
+        entries = [];
+    }
+    render();
+}
+
+function render() {
+    const list = document.getElementById('history-entries');
+    list.innerHTML = '';
+
+    if (entries === null) {
+        shown = [];
+        list.appendChild(message('Clipboard history is off. Turn it on in Settings.'));
+        return;
+    }
+
+    const query = document.getElementById('history-filter').value.trim().toLowerCase();
+    shown = entries.filter(entry => entry.text.toLowerCase().includes(query));
+    selected = Math.min(selected, Math.max(shown.length - 1, 0));
+
+    if (shown.length === 0) {
+        list.appendChild(message(entries.length === 0 ? 'Nothing copied yet' : 'No matches'));
+        return;
+    }
+
+    shown.forEach((entry, i) => {
+        const li = document.createElement('li');
+        li.className = 'history-item' + (i === selected ? ' selected' : '');
+        li.innerHTML = `
+            <div class="history-text">
+                <span class="history-preview">${escapeHtml(preview(entry.text))}</span>
+                <span class="history-detail">${timeAgo(entry.at)} · ${entry.text.length} chars</span>
+            </div>
+            <button type="button" class="history-action pin ${entry.pinned ? 'active' : ''}" title="${entry.pinned ? 'Unpin' : 'Pin'}">📌</button>
+            <button type="button" class="history-action delete" title="Delete">✕</button>
+        `;
+        li.addEventListener('mousemove', () => {
+            if (selected !== i) select(i);
+        });
+        li.addEventListener('click', (e) => {
+            if (e.target.closest('.pin')) pin(entry);
+            else if (e.target.closest('.delete')) remove(entry);
+            else paste(entry);
+        });
+        list.appendChild(li);
+    });
+}
+
+function message(text) {
+    const li = document.createElement('li');
+    li.className = 'history-empty';
+    li.textContent = text;
+    return li;
+}
+
+function select(index) {
+    if (shown.length === 0) return;
+    selected = (index + shown.length) % shown.length;
+    const list = document.getElementById('history-entries');
+    list.querySelectorAll('.history-item').forEach((li, i) => {
+        li.classList.toggle('selected', i === selected);
+    });
+    list.children[selected]?.scrollIntoView({ block: 'nearest' });
+}
+
+async function paste(entry) {
+    try {
+        await invoke('paste_clipboard_entry', { id: entry.id });
+    } catch (error) {
+        console.error('Failed to paste entry:', error);
+    }
+}
+
+async function pin(entry) {
+    try {
+        await invoke('pin_clipboard_entry', { id: entry.id, pinned: !entry.pinned });
+    } catch (error) {
+        console.error('Failed to pin entry:', error);
+    }
+    document.getElementById('history-filter').focus();
+}
+
+async function remove(entry) {
+    try {
+        await invoke('delete_clipboard_entry', { id: entry.id });
+    } catch (error) {
+        console.error('Failed to delete entry:', error);
+    }
+    document.getElementById('history-filter').focus();
+}
+
+async function hide() {
+    try {
+        await invoke('hide_clipboard_history');
+    } catch (error) {
+        console.error('Failed to hide clipboard history:', error);
+    }
+}
+
+// One line: whitespace collapsed, long text cut
+function preview(text) {
+    return text.trim().replace(/\s+/g, ' ').slice(0, 200);
+}
+
+function timeAgo(timestamp) {
+    const seconds = Math.max(0, Math.round((Date.now() - timestamp) / 1000));
+    if (seconds < 60) return 'just now';
+    if (seconds < 3600) return `${Math.floor(seconds / 60)} min ago`;
+    if (seconds < 86400) return `${Math.floor(seconds / 3600)} h ago`;
+    return `${Math.floor(seconds / 86400)} d ago`;
+}
+
+function escapeHtml(str) {
+    const div = document.createElement('div');
+    div.textContent = str;
+    return div.innerHTML;
+}
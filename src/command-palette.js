@@ -0,0 +1,129 @@
+// Command Palette
+
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+let items = [];
+let selected = 0;
+
+document.addEventListener('DOMContentLoaded', init);
+
+async function init() {
+    const input = document.getElementById('palette-input');
+
+    input.addEventListener('input', () => refresh(input.value));
+    input.addEventListener('keydown', (e) => {
+        if (e.key === 'ArrowDown') {
+            e.preventDefault();
+            select(selected + 1);
+        } else if (e.key === 'ArrowUp') {
+            e.preventDefault();
+            select(selected - 1);
+        } else if (e.key === 'Enter') {
+            e.preventDefault();
+            if (items[selected]) run(items[selected]);
+        } else if (e.key === 'Escape') {
+            e.preventDefault();
+            hide();
+        }
+    });
+
+    // Hide when the user clicks elsewhere
+    window.addEventListener('blur', hide);
+
+    // The window is reused; start over each time it is shown
+    await listen('command-palette-shown', () => {
+        input.value = '';
+        refresh('');
+        input.focus();
+    });
+
+    refresh('');
+    input.focus();
+}
+
+async function refresh(query) {
+    try {
+        items = await invoke('search_palette', { query });
+    } catch (error) {
+        console.error('Palette search failed:', error);
+        items = [];
+    }
+    // Ignore results for a query the user has already typed past
+    if (query !== document.getElementById('palette-input').value) return;
+    selected = 0;
+    render();
+}
+
+function render() {
+    const list = document.getElementById('palette-results');
+    list.innerHTML = '';
+
+    if (items.length === 0) {
+        const empty = document.createElement('li');
+        empty.className = 'palette-empty';
+        empty.textContent = 'No matches';
+        list.appendChild(empty);
+        return;
+    }
+
+    items.forEach((item, i) => {
+        const li = document.createElement('li');
+        li.className = 'palette-item' + (i === selected ? ' selected' : '');
+        li.innerHTML = `
+            <div class="palette-text">
+                <span class="palette-title">${escapeHtml(item.title)}</span>
+                ${item.detail ? `<span class="palette-detail">${escapeHtml(item.detail)}</span>` : ''}
+            </div>
+            ${item.shortcut ? `<kbd>${escapeHtml(formatShortcut(item.shortcut))}</kbd>` : ''}
+        `;
+        li.addEventListener('mousemove', () => {
+            if (selected !== i) select(i);
+        });
+        li.addEventListener('click', () => run(item));
+        list.appendChild(li);
+    });
+}
+
+function select(index) {
+    if (items.length === 0) return;
+    selected = (index + items.length) % items.length;
+    const list = document.getElementById('palette-results');
+    list.querySelectorAll('.palette-item').forEach((li, i) => {
+        li.classList.toggle('selected', i === selected);
+    });
+    list.children[selected]?.scrollIntoView({ block: 'nearest' });
+}
+
+async function run(item) {
+    try {
+        await invoke('run_palette_action', { action: item.action });
+    } catch (error) {
+        console.error('Failed to run palette item:', error);
+    }
+}
+
+async function hide() {
+    try {
+        await invoke('hide_command_palette');
+    } catch (error) {
+        console.error('Failed to hide palette:', error);
+    }
+}
+
+const IS_MAC = navigator.platform.toUpperCase().includes('MAC');
+
+// "CmdOrCtrl+Shift+P" -> "⌘⇧P" on macOS, "Ctrl+Shift+P" elsewhere
+function formatShortcut(accelerator) {
+    const mac = { CmdOrCtrl: '⌘', CommandOrControl: '⌘', Cmd: '⌘', Super: '⌘', Ctrl: '⌃', Alt: '⌥', Shift: '⇧', Comma: ',' };
+    const other = { CmdOrCtrl: 'Ctrl', CommandOrControl: 'Ctrl', Cmd: 'Ctrl', Comma: ',' };
+    const names = IS_MAC ? mac : other;
+    const parts = accelerator.split('+').map(part => names[part] ?? part);
+    return parts.join(IS_MAC ? '' : '+');
+}
+
+function escapeHtml(str) {
+    const div = document.createElement('div');
+    div.textContent = str;
+    return div.innerHTML;
+}
@@ -0,0 +1,47 @@
+// Lock window: asks for the passphrase while Peko is locked
+
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+document.addEventListener('DOMContentLoaded', init);
+
+async function init() {
+    const form = document.getElementById('unlock-form');
+    const input = document.getElementById('passphrase');
+
+    form.addEventListener('submit', async (e) => {
+        e.preventDefault();
+        await unlock();
+    });
+
+    // Start over every time the app locks
+    await listen('lock-shown', () => {
+        input.value = '';
+        showError('');
+        input.focus();
+    });
+
+    input.focus();
+}
+
+async function unlock() {
+    const input = document.getElementById('passphrase');
+    const button = document.getElementById('unlock-btn');
+    if (!input.value) return;
+
+    button.disabled = true;
+    showError('');
+    try {
+        await invoke('unlock', { passphrase: input.value });
+        input.value = '';
+    } catch (error) {
+        input.select();
+        showError(String(error));
+    } finally {
+        button.disabled = false;
+    }
+}
+
+function showError(message) {
+    document.getElementById('lock-error').textContent = message;
+}
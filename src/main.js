@@ -7,6 +7,19 @@ const EMOJIS = ['✨', '📓', '🌐', '💬', '🔍', '📧', '🎵', '📺', '
 
 let websites = [];
 let defaultWebsite = null;
+let globalShortcuts = []; // [{ accelerator, action: { type, site? } }]
+let keymap = []; // [{ action, label, accelerator, default }]
+let autoPasteSelector = '';
+let sendTemplate = '';
+let clipboardHistory = { enabled: false, persist: false, limit: 50 };
+let snippets = []; // [{ id, name, body, sites, accelerator }]
+let filterLists = '';
+let blockerStatus = { lists: [], blocked: {} };
+let profiles = []; // [{ id, name, default_store }]
+let savedSites = new Set(); // Sites whose windows exist, through which data is cleared
+let profileDataUsage = null; // { profile: bytes }, null where the size isn't known
+let lockEnabled = false; // Whether a lock passphrase is set
+let lockIdleMinutes = 15;
 let isComposing = false; // Track IME composition state for Chinese/Japanese/Korean input
 
 document.addEventListener('DOMContentLoaded', init);
@@ -16,10 +29,28 @@ async function init() {
     const settings = await invoke('get_settings');
     websites = settings.websites || [];
     defaultWebsite = settings.default_website || (websites[0]?.id ?? null);
+    globalShortcuts = settings.global_shortcuts || [];
+    autoPasteSelector = settings.auto_paste_selector || '';
+    sendTemplate = settings.send_template || '';
+    clipboardHistory = {
+      enabled: settings.clipboard_history,
+      persist: settings.clipboard_history_persist,
+      limit: settings.clipboard_history_limit,
+    };
+    snippets = settings.snippets || [];
+    filterLists = (settings.filter_lists || []).join('\n');
+    keymap = await invoke('get_keymap');
+    blockerStatus = await invoke('get_blocker_status');
+    profiles = settings.profiles || [];
+    savedSites = new Set(websites.map(w => w.id));
+    profileDataUsage = await invoke('get_profile_data_usage');
+    lockEnabled = settings.has_lock_passphrase;
+    lockIdleMinutes = settings.lock_idle_minutes;
   } catch (error) {
     console.error('Failed to load settings:', error);
     websites = [];
     defaultWebsite = null;
+    globalShortcuts = [];
   }
 
   render();
@@ -28,10 +59,36 @@ async function init() {
 
 function setupEventListeners() {
   document.getElementById('add-btn').addEventListener('click', addWebsite);
+  document.getElementById('add-snippet-btn').addEventListener('click', addSnippet);
+  document.getElementById('add-profile-btn').addEventListener('click', addProfile);
   document.getElementById('save-btn').addEventListener('click', saveAndClose);
   document.getElementById('default-website').addEventListener('change', (e) => {
     defaultWebsite = e.target.value;
   });
+  document.getElementById('auto-paste-selector').addEventListener('input', (e) => {
+    autoPasteSelector = e.target.value;
+  });
+  document.getElementById('send-template').addEventListener('input', (e) => {
+    sendTemplate = e.target.value;
+  });
+  document.getElementById('filter-lists').addEventListener('input', (e) => {
+    filterLists = e.target.value;
+  });
+  document.getElementById('clipboard-history-enabled').addEventListener('change', (e) => {
+    clipboardHistory.enabled = e.target.checked;
+    renderClipboardHistory();
+  });
+  document.getElementById('clipboard-history-persist').addEventListener('change', (e) => {
+    clipboardHistory.persist = e.target.checked;
+  });
+  document.getElementById('clipboard-history-limit').addEventListener('input', (e) => {
+    clipboardHistory.limit = parseInt(e.target.value) || 0;
+  });
+  document.getElementById('lock-set-btn').addEventListener('click', setLockPassphrase);
+  document.getElementById('lock-remove-btn').addEventListener('click', removeLock);
+  document.getElementById('lock-idle-minutes').addEventListener('input', (e) => {
+    lockIdleMinutes = parseInt(e.target.value) || 0;
+  });
 
   // Close emoji pickers on outside click
   document.addEventListener('click', (e) => {
@@ -85,6 +142,22 @@ function render() {
       <div class="website-fields">
         <input type="text" class="input-name" placeholder="Name" value="${escapeHtml(website.name)}" data-index="${index}" data-field="name">
         <input type="url" class="input-url" placeholder="https://example.com" value="${escapeHtml(website.url)}" data-index="${index}" data-field="url">
+        <input type="text" class="input-selector" placeholder="Main input selector (optional)" value="${escapeHtml(website.input_selector ?? '')}" data-index="${index}" data-field="input_selector" spellcheck="false">
+        <label class="website-option">
+          <input type="checkbox" ${website.auto_paste ? 'checked' : ''} data-index="${index}" data-field="auto_paste">
+          Auto-paste on focus
+        </label>
+        <label class="website-option">
+          <input type="checkbox" ${website.content_blocking !== false ? 'checked' : ''} data-index="${index}" data-field="content_blocking">
+          Block ads and trackers
+        </label>
+        <label class="website-option">
+          Profile
+          <select class="default-select site-profile" data-index="${index}">
+            <option value="">Its own</option>
+            ${profiles.map(p => `<option value="${p.id}" ${p.id === website.profile ? 'selected' : ''}>${escapeHtml(p.name || 'Unnamed')}</option>`).join('')}
+          </select>
+        </label>
       </div>
       <button class="delete-btn" data-index="${index}" title="Remove">🗑️</button>
     `;
@@ -96,7 +169,13 @@ function render() {
     input.addEventListener('input', (e) => {
       const index = parseInt(e.target.dataset.index);
       const field = e.target.dataset.field;
-      websites[index][field] = e.target.value;
+      if (e.target.type === 'checkbox') {
+        websites[index][field] = e.target.checked;
+      } else if (field === 'input_selector') {
+        websites[index][field] = e.target.value.trim() || null;
+      } else {
+        websites[index][field] = e.target.value;
+      }
       // Update default dropdown if name changes
       if (field === 'name') renderDefaultSelector();
     });
@@ -116,6 +195,13 @@ function render() {
     });
   });
 
+  list.querySelectorAll('.site-profile').forEach(select => {
+    select.addEventListener('change', (e) => {
+      websites[parseInt(e.target.dataset.index)].profile = e.target.value;
+      renderProfiles();
+    });
+  });
+
   // Emoji buttons
   list.querySelectorAll('.website-emoji').forEach(btn => {
     btn.addEventListener('click', (e) => {
@@ -127,8 +213,341 @@ function render() {
   // Update add button state
   document.getElementById('add-btn').disabled = websites.length >= 5;
 
+  document.getElementById('auto-paste-selector').value = autoPasteSelector;
+  document.getElementById('send-template').value = sendTemplate;
+  document.getElementById('filter-lists').value = filterLists;
+  renderProfiles();
+  renderBlockerStatus();
+  renderClipboardHistory();
+  renderLock();
+  renderSnippets();
+
   // Render default website selector
   renderDefaultSelector();
+  renderKeymap();
+  renderShortcuts();
+}
+
+// ===== Profiles =====
+
+function renderProfiles() {
+  const list = document.getElementById('profiles-list');
+  list.innerHTML = '';
+
+  profiles.forEach((profile, index) => {
+    const users = websites.filter(w => w.profile === profile.id);
+    const item = document.createElement('div');
+    item.className = 'snippet-item';
+    item.innerHTML = `
+      <div class="snippet-header">
+        <input type="text" class="selector-input" placeholder="Name" value="${escapeHtml(profile.name)}">
+        <button class="delete-btn" title="${users.length > 0 ? 'In use' : 'Remove'}" ${users.length > 0 ? 'disabled' : ''}>🗑️</button>
+      </div>
+      <div class="profile-data">
+        <span class="profile-data-usage">${profileUsageText(profile, users)}</span>
+        ${users.some(w => savedSites.has(w.id)) ? `
+          <select class="default-select profile-data-kind">
+            <option value="cookies">Cookies</option>
+            <option value="cache">Cache</option>
+            <option value="storage">Storage</option>
+            <option value="all">Everything</option>
+          </select>
+          <button class="btn btn-secondary profile-data-clear">Clear</button>
+        ` : ''}
+      </div>
+    `;
+
+    const name = item.querySelector('.selector-input');
+    name.addEventListener('input', (e) => {
+      profile.name = e.target.value;
+    });
+    // Update the site rows' profile lists once editing is done
+    name.addEventListener('change', render);
+    item.querySelector('.delete-btn').addEventListener('click', () => {
+      profiles.splice(index, 1);
+      render();
+    });
+    item.querySelector('.profile-data-clear')?.addEventListener('click', () => {
+      clearProfileData(profile, item.querySelector('.profile-data-kind'));
+    });
+    list.appendChild(item);
+  });
+}
+
+function profileUsageText(profile, users) {
+  const sites = users.length > 0
+    ? users.map(w => `${w.emoji} ${escapeHtml(w.name)}`).join(', ')
+    : 'No sites';
+  const usage = profileDataUsage?.[profile.id];
+  return usage === undefined ? sites : `${sites} · ${formatBytes(usage)}`;
+}
+
+function addProfile() {
+  profiles.push({ id: `profile_${Date.now()}`, name: '', default_store: false });
+  renderProfiles();
+
+  const inputs = document.querySelectorAll('#profiles-list .selector-input');
+  inputs[inputs.length - 1]?.focus();
+}
+
+async function clearProfileData(profile, select) {
+  const label = select.options[select.selectedIndex].text.toLowerCase();
+  if (!confirm(`Clear ${label} of the ${profile.name} profile? Its sites will reload.`)) return;
+
+  try {
+    await invoke('clear_profile_data', { profile: profile.id, kind: select.value });
+    profileDataUsage = await invoke('get_profile_data_usage');
+  } catch (error) {
+    alert('Failed to clear profile data: ' + error);
+  }
+  renderProfiles();
+}
+
+function formatBytes(bytes) {
+  const units = ['B', 'KB', 'MB', 'GB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit++;
+  }
+  return `${unit === 0 ? value : value.toFixed(1)} ${units[unit]}`;
+}
+
+// ===== Content Blocking =====
+
+function renderBlockerStatus() {
+  const status = document.getElementById('blocker-status');
+  const lines = blockerStatus.lists.map(list => list.error
+    ? `${list.path}: ${list.error}`
+    : `${list.path}: ${list.network_filters} network and ${list.cosmetic_filters} cosmetic filters` +
+      (list.skipped ? `, ${list.skipped} unsupported` : ''));
+
+  const blocked = websites
+    .filter(w => blockerStatus.blocked[w.id])
+    .map(w => `${w.emoji} ${w.name}: ${blockerStatus.blocked[w.id]}`);
+  if (blocked.length > 0) {
+    lines.push(`Blocked since launch: ${blocked.join(', ')}`);
+  }
+  status.textContent = lines.join('\n');
+  status.classList.toggle('hidden', lines.length === 0);
+}
+
+// ===== Snippets =====
+
+function renderSnippets() {
+  const list = document.getElementById('snippets-list');
+  list.innerHTML = '';
+
+  snippets.forEach((snippet, index) => {
+    const item = document.createElement('div');
+    item.className = 'snippet-item';
+    item.innerHTML = `
+      <div class="snippet-header">
+        <input type="text" class="selector-input" placeholder="Name" value="${escapeHtml(snippet.name)}" data-field="name">
+        <button class="delete-btn" title="Remove">🗑️</button>
+      </div>
+      <textarea class="snippet-body" placeholder="Explain this: {{selection}}" spellcheck="false" data-field="body">${escapeHtml(snippet.body)}</textarea>
+      <div class="snippet-sites">
+        ${websites.filter(w => w.name.trim()).map(w => `
+          <label class="website-option">
+            <input type="checkbox" ${snippet.sites.includes(w.id) ? 'checked' : ''} data-site="${w.id}">
+            ${w.emoji} ${escapeHtml(w.name)}
+          </label>
+        `).join('')}
+      </div>
+    `;
+
+    item.querySelectorAll('[data-field]').forEach(input => {
+      input.addEventListener('input', (e) => {
+        snippet[e.target.dataset.field] = e.target.value;
+      });
+    });
+    item.querySelectorAll('[data-site]').forEach(checkbox => {
+      checkbox.addEventListener('change', (e) => {
+        const site = e.target.dataset.site;
+        snippet.sites = snippet.sites.filter(s => s !== site);
+        if (e.target.checked) snippet.sites.push(site);
+      });
+    });
+    item.querySelector('.delete-btn').addEventListener('click', () => {
+      snippets.splice(index, 1);
+      renderSnippets();
+    });
+    item.appendChild(shortcutRow('Shortcut', snippet.accelerator, (accelerator) => {
+      snippet.accelerator = accelerator;
+    }));
+
+    list.appendChild(item);
+  });
+}
+
+function addSnippet() {
+  snippets.push({ id: `snippet_${Date.now()}`, name: '', body: '', sites: [], accelerator: null });
+  renderSnippets();
+
+  const inputs = document.querySelectorAll('.snippet-item .selector-input');
+  inputs[inputs.length - 1]?.focus();
+}
+
+// ===== Keyboard Shortcuts (menu) =====
+
+function renderKeymap() {
+  const list = document.getElementById('keymap-list');
+  list.innerHTML = '';
+
+  keymap.forEach(binding => {
+    list.appendChild(shortcutRow(binding.label, binding.accelerator, (accelerator) => {
+      binding.accelerator = accelerator;
+    }));
+  });
+}
+
+// ===== Global Shortcuts =====
+
+const MODIFIER_CODES = ['ShiftLeft', 'ShiftRight', 'ControlLeft', 'ControlRight', 'AltLeft', 'AltRight', 'MetaLeft', 'MetaRight'];
+
+function sameAction(a, b) {
+  return a.type === b.type && (a.site ?? null) === (b.site ?? null);
+}
+
+function shortcutActions() {
+  const actions = [
+    { label: 'Show Peko', action: { type: 'summon' } },
+    { label: 'Toggle Notes', action: { type: 'toggle_notes' } },
+  ];
+  websites.filter(w => w.name.trim()).forEach(w => {
+    actions.push({ label: `Open ${w.emoji} ${w.name}`, action: { type: 'open_site', site: w.id } });
+  });
+  return actions;
+}
+
+function renderShortcuts() {
+  const list = document.getElementById('shortcuts-list');
+  list.innerHTML = '';
+
+  shortcutActions().forEach(({ label, action }) => {
+    const current = globalShortcuts.find(s => sameAction(s.action, action));
+    list.appendChild(shortcutRow(label, current?.accelerator, (accelerator) => {
+      setShortcut(action, accelerator);
+    }));
+  });
+}
+
+// A labelled field that records a key combination. Backspace or Delete
+// clears it; `onChange` gets the accelerator or null.
+function shortcutRow(label, accelerator, onChange) {
+  const row = document.createElement('div');
+  row.className = 'shortcut-row';
+  row.innerHTML = `
+    <label>${escapeHtml(label)}</label>
+    <input type="text" class="shortcut-input" readonly placeholder="None" value="${escapeHtml(accelerator ?? '')}">
+  `;
+  const input = row.querySelector('input');
+  input.addEventListener('keydown', (e) => {
+    if (e.key === 'Tab') return;
+    e.preventDefault();
+    if (e.key === 'Escape') {
+      input.blur();
+      return;
+    }
+
+    const noModifiers = !e.ctrlKey && !e.altKey && !e.shiftKey && !e.metaKey;
+    if (noModifiers && (e.key === 'Backspace' || e.key === 'Delete')) {
+      onChange(null);
+      input.value = '';
+      return;
+    }
+
+    const accelerator = acceleratorFromEvent(e);
+    if (accelerator) {
+      onChange(accelerator);
+      input.value = accelerator;
+    }
+  });
+  return row;
+}
+
+// Build an accelerator such as "Super+Shift+G" from a key press.
+// Plain keys are ignored: shortcuts need a modifier.
+function acceleratorFromEvent(e) {
+  if (MODIFIER_CODES.includes(e.code)) return null;
+
+  const parts = [];
+  if (e.ctrlKey) parts.push('Ctrl');
+  if (e.altKey) parts.push('Alt');
+  if (e.shiftKey) parts.push('Shift');
+  if (e.metaKey) parts.push('Super');
+  if (parts.length === 0) return null;
+
+  parts.push(e.code.replace(/^Key/, '').replace(/^Digit/, ''));
+  return parts.join('+');
+}
+
+function setShortcut(action, accelerator) {
+  globalShortcuts = globalShortcuts.filter(s => !sameAction(s.action, action));
+  if (accelerator) {
+    globalShortcuts.push({ accelerator, action });
+  }
+}
+
+function showShortcutStatus(message) {
+  const status = document.getElementById('shortcut-status');
+  status.textContent = message;
+  status.classList.toggle('hidden', !message);
+}
+
+function renderClipboardHistory() {
+  document.getElementById('clipboard-history-enabled').checked = clipboardHistory.enabled;
+  document.getElementById('clipboard-history-persist').checked = clipboardHistory.persist;
+  document.getElementById('clipboard-history-persist').disabled = !clipboardHistory.enabled;
+  document.getElementById('clipboard-history-limit').value = clipboardHistory.limit;
+}
+
+// ===== App Lock =====
+
+function renderLock(status = '') {
+  document.getElementById('lock-current').classList.toggle('hidden', !lockEnabled);
+  document.getElementById('lock-set-btn').textContent = lockEnabled ? 'Change Passphrase' : 'Set Passphrase';
+  document.getElementById('lock-remove-btn').classList.toggle('hidden', !lockEnabled);
+  document.getElementById('lock-idle-minutes').value = lockIdleMinutes;
+  document.getElementById('lock-status').textContent = status || (lockEnabled ? 'Lock is on' : 'Lock is off');
+  for (const id of ['lock-current', 'lock-new', 'lock-confirm']) {
+    document.getElementById(id).value = '';
+  }
+}
+
+function showLockStatus(message) {
+  document.getElementById('lock-status').textContent = message;
+}
+
+// Passphrase changes are saved right away, not with Save & Close
+async function setLockPassphrase() {
+  const current = document.getElementById('lock-current').value;
+  const next = document.getElementById('lock-new').value;
+  if (!next) return;
+  if (next !== document.getElementById('lock-confirm').value) {
+    showLockStatus('The new passphrases don\'t match');
+    return;
+  }
+  try {
+    await invoke('set_lock_passphrase', { current, new: next });
+    lockEnabled = true;
+    renderLock('Passphrase saved');
+  } catch (error) {
+    showLockStatus(String(error));
+  }
+}
+
+async function removeLock() {
+  const current = document.getElementById('lock-current').value;
+  try {
+    await invoke('set_lock_passphrase', { current, new: '' });
+    lockEnabled = false;
+    renderLock('Lock removed');
+  } catch (error) {
+    showLockStatus(String(error));
+  }
 }
 
 function renderDefaultSelector() {
@@ -154,7 +573,11 @@ function addWebsite() {
     id,
     name: '',
     url: '',
-    emoji: EMOJIS[websites.length % EMOJIS.length]
+    emoji: EMOJIS[websites.length % EMOJIS.length],
+    auto_paste: false,
+    input_selector: null,
+    content_blocking: true,
+    profile: ''
   });
 
   render();
@@ -213,11 +636,45 @@ async function saveAndClose() {
   });
 
   try {
-    await invoke('save_websites', { websites: valid });
+    // Unnamed profiles no site uses are dropped
+    await invoke('save_websites', {
+      websites: valid,
+      profiles: profiles.filter(p => p.name.trim() || valid.some(w => w.profile === p.id)),
+    });
     // Save default website if valid
     if (defaultWebsite && valid.find(w => w.id === defaultWebsite)) {
       await invoke('save_default_website', { websiteId: defaultWebsite });
     }
+    await invoke('save_auto_paste_selector', { selector: autoPasteSelector });
+    await invoke('save_send_template', { template: sendTemplate });
+    blockerStatus = await invoke('save_filter_lists', { paths: filterLists.split('\n') });
+    renderBlockerStatus();
+    await invoke('save_clipboard_history_settings', clipboardHistory);
+    await invoke('save_lock_idle_minutes', { minutes: lockIdleMinutes });
+
+    // Before the global shortcuts, which are checked against the menu
+    await invoke('save_keymap', {
+      keymap: Object.fromEntries(keymap.map(b => [b.action, b.accelerator])),
+    });
+    // Unnamed snippets are dropped, like blank websites
+    await invoke('save_snippets', {
+      snippets: snippets
+        .filter(s => s.name.trim())
+        .map(s => ({ ...s, sites: s.sites.filter(site => valid.some(w => w.id === site)) })),
+    });
+
+    // Drop shortcuts for sites that were removed or left blank
+    const shortcuts = globalShortcuts.filter(s =>
+      s.action.type !== 'open_site' || valid.some(w => w.id === s.action.site)
+    );
+    showShortcutStatus('');
+    const conflicts = await invoke('save_global_shortcuts', { shortcuts });
+    if (conflicts.length > 0) {
+      // Saved, but keep the window open so the user sees what didn't register
+      showShortcutStatus(conflicts.map(c => `${c.accelerator}: ${c.reason}`).join('\n'));
+      return;
+    }
+
     const win = getCurrentWindow();
     await win.close();
   } catch (error) {
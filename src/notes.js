@@ -1,8 +1,12 @@
 // Notes Panel
 
 const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
 
 let saveTimeout = null;
+let searchTimeout = null;
+let searchHits = [];
+let selectedHit = -1;
 
 document.addEventListener('DOMContentLoaded', init);
 
@@ -25,6 +29,7 @@ async function init() {
 
         if (saveTimeout) clearTimeout(saveTimeout);
         saveTimeout = setTimeout(async () => {
+            saveTimeout = null;
             try {
                 await invoke('save_notes', { content: editor.value });
             } catch (error) {
@@ -33,26 +38,169 @@ async function init() {
         }, 500);
     });
 
+    // Content appended by the backend (e.g. Clip to Notes)
+    await listen('notes-updated', (event) => {
+        if (saveTimeout) {
+            // Unsaved local edits: append the entry so the pending save keeps both
+            const existing = editor.value.trimEnd();
+            editor.value = existing ? `${existing}\n\n${event.payload.entry}` : event.payload.entry;
+        } else {
+            editor.value = event.payload.content;
+        }
+        renderPreview();
+    });
+
     // Tab switching
     document.querySelectorAll('.tab-btn').forEach(btn => {
-        btn.addEventListener('click', () => {
-            const mode = btn.dataset.mode;
-
-            document.querySelectorAll('.tab-btn').forEach(b => b.classList.remove('active'));
-            btn.classList.add('active');
-
-            if (mode === 'edit') {
-                editor.classList.remove('hidden');
-                preview.classList.add('hidden');
-            } else {
-                editor.classList.add('hidden');
-                preview.classList.remove('hidden');
-                renderPreview();
-            }
-        });
+        btn.addEventListener('click', () => setMode(btn.dataset.mode));
+    });
+
+    setupSearch();
+    setupExport();
+}
+
+function setupExport() {
+    const select = document.getElementById('notes-export');
+    const status = document.getElementById('export-status');
+
+    select.addEventListener('change', async () => {
+        const format = select.value;
+        select.value = '';
+
+        try {
+            const path = await invoke('export_notes', { format });
+            status.textContent = `Exported to ${path}`;
+        } catch (error) {
+            console.error('Failed to export notes:', error);
+            status.textContent = `Export failed: ${error}`;
+        }
+        status.classList.remove('hidden');
     });
 }
 
+function setMode(mode) {
+    const editor = document.getElementById('notes-editor');
+    const preview = document.getElementById('notes-preview');
+
+    document.querySelectorAll('.tab-btn').forEach(b => {
+        b.classList.toggle('active', b.dataset.mode === mode);
+    });
+
+    if (mode === 'edit') {
+        editor.classList.remove('hidden');
+        preview.classList.add('hidden');
+    } else {
+        editor.classList.add('hidden');
+        preview.classList.remove('hidden');
+        renderPreview();
+    }
+}
+
+function setupSearch() {
+    const input = document.getElementById('notes-search');
+    const results = document.getElementById('search-results');
+
+    input.addEventListener('input', () => {
+        if (searchTimeout) clearTimeout(searchTimeout);
+        searchTimeout = setTimeout(() => runSearch(input.value), 150);
+    });
+
+    input.addEventListener('keydown', (e) => {
+        if (e.key === 'ArrowDown' || e.key === 'ArrowUp') {
+            e.preventDefault();
+            if (searchHits.length === 0) return;
+            const step = e.key === 'ArrowDown' ? 1 : -1;
+            selectedHit = (selectedHit + step + searchHits.length) % searchHits.length;
+            renderSearchResults();
+        } else if (e.key === 'Enter') {
+            e.preventDefault();
+            const hit = searchHits[Math.max(selectedHit, 0)];
+            if (hit) jumpToHit(hit);
+        } else if (e.key === 'Escape') {
+            input.value = '';
+            clearSearch();
+        }
+    });
+
+    document.addEventListener('click', (e) => {
+        if (!e.target.closest('.notes-search')) {
+            results.classList.add('hidden');
+        }
+    });
+}
+
+async function runSearch(query) {
+    if (!query.trim()) {
+        clearSearch();
+        return;
+    }
+
+    try {
+        searchHits = await invoke('search_notes', { query, limit: 50 });
+    } catch (error) {
+        console.error('Failed to search notes:', error);
+        searchHits = [];
+    }
+    selectedHit = searchHits.length > 0 ? 0 : -1;
+    renderSearchResults();
+}
+
+function clearSearch() {
+    searchHits = [];
+    selectedHit = -1;
+    document.getElementById('search-results').classList.add('hidden');
+}
+
+function renderSearchResults() {
+    const results = document.getElementById('search-results');
+    results.innerHTML = '';
+
+    if (searchHits.length === 0) {
+        const empty = document.createElement('li');
+        empty.className = 'search-empty';
+        empty.textContent = 'No matches';
+        results.appendChild(empty);
+    }
+
+    searchHits.forEach((hit, index) => {
+        const item = document.createElement('li');
+        item.className = 'search-hit' + (index === selectedHit ? ' selected' : '');
+
+        const line = document.createElement('span');
+        line.className = 'search-hit-line';
+        line.textContent = `L${hit.line}`;
+
+        item.appendChild(line);
+        item.appendChild(document.createTextNode(hit.snippet));
+        item.addEventListener('click', () => jumpToHit(hit));
+        results.appendChild(item);
+    });
+
+    results.classList.remove('hidden');
+    results.querySelector('.selected')?.scrollIntoView({ block: 'nearest' });
+}
+
+function jumpToHit(hit) {
+    const editor = document.getElementById('notes-editor');
+    const lines = editor.value.split('\n');
+    const lineIndex = Math.min(hit.line, lines.length) - 1;
+
+    let start = 0;
+    for (let i = 0; i < lineIndex; i++) {
+        start += lines[i].length + 1;
+    }
+    const end = start + (lines[lineIndex] || '').length;
+
+    setMode('edit');
+    clearSearch();
+    editor.focus();
+    editor.setSelectionRange(start, end);
+
+    // Scroll the selected line into view
+    const lineHeight = parseFloat(getComputedStyle(editor).lineHeight) || 20;
+    editor.scrollTop = Math.max(0, lineIndex * lineHeight - editor.clientHeight / 2);
+}
+
 function renderPreview() {
     const editor = document.getElementById('notes-editor');
     const preview = document.getElementById('notes-preview');
@@ -0,0 +1,151 @@
+// Tab Switcher
+//
+// Opens on the most recently used tab other than the current one. While
+// Ctrl/Cmd is held, Tab and Shift+Tab move the selection and releasing the
+// modifier switches, like Alt+Tab between apps. Opened with a quick tap it
+// stays up for arrow keys, 1-9, Enter or a click.
+
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+const HOLD_KEYS = ['Control', 'Meta', 'Alt'];
+
+let tabs = [];
+let selected = 0;
+let holding = false;
+
+document.addEventListener('DOMContentLoaded', init);
+
+async function init() {
+    document.addEventListener('keydown', onKeyDown);
+    document.addEventListener('keyup', (e) => {
+        if (holding && HOLD_KEYS.includes(e.key)) {
+            commit();
+        }
+    });
+
+    window.addEventListener('blur', hide);
+
+    await listen('tab-switcher-shown', load);
+    await load();
+}
+
+async function load() {
+    holding = false;
+    try {
+        tabs = await invoke('get_switcher_tabs');
+    } catch (error) {
+        console.error('Failed to load tabs:', error);
+        tabs = [];
+    }
+    selected = tabs.length > 1 ? 1 : 0;
+    render();
+}
+
+function onKeyDown(e) {
+    if (e.ctrlKey || e.metaKey || e.altKey) {
+        holding = true;
+    }
+
+    if (e.key === 'Tab') {
+        e.preventDefault();
+        select(selected + (e.shiftKey ? -1 : 1));
+    } else if (e.key === 'ArrowRight' || e.key === 'ArrowDown') {
+        e.preventDefault();
+        select(selected + 1);
+    } else if (e.key === 'ArrowLeft' || e.key === 'ArrowUp') {
+        e.preventDefault();
+        select(selected - 1);
+    } else if (e.key === 'Enter') {
+        e.preventDefault();
+        commit();
+    } else if (e.key === 'Escape') {
+        e.preventDefault();
+        hide();
+    } else if (/^[1-9]$/.test(e.key) && Number(e.key) <= tabs.length) {
+        e.preventDefault();
+        selected = Number(e.key) - 1;
+        commit();
+    }
+}
+
+function render() {
+    const list = document.getElementById('switcher-tabs');
+    list.innerHTML = '';
+
+    tabs.forEach((tab, i) => {
+        const li = document.createElement('li');
+        li.className = 'switcher-tab' + (i === selected ? ' selected' : '') + (tab.active ? ' active' : '');
+        li.innerHTML = `
+            <div class="switcher-icon">
+                <span class="switcher-emoji">${escapeHtml(tab.emoji)}</span>
+            </div>
+            <span class="switcher-name">${escapeHtml(tab.name)}</span>
+            <span class="switcher-time">${escapeHtml(timeAgo(tab.last_active))}</span>
+        `;
+        if (tab.favicon) {
+            // Show the favicon once it loads; keep the emoji if it doesn't
+            const img = new Image();
+            img.className = 'switcher-favicon';
+            img.onload = () => li.querySelector('.switcher-icon').replaceChildren(img);
+            img.src = tab.favicon;
+        }
+        li.addEventListener('click', () => {
+            selected = i;
+            commit();
+        });
+        list.appendChild(li);
+    });
+
+    renderCaption();
+}
+
+function renderCaption() {
+    const tab = tabs[selected];
+    document.getElementById('switcher-caption').textContent = tab ? (tab.title || tab.url || tab.name) : 'No tabs';
+}
+
+function select(index) {
+    if (tabs.length === 0) return;
+    selected = (index + tabs.length) % tabs.length;
+    document.querySelectorAll('.switcher-tab').forEach((li, i) => {
+        li.classList.toggle('selected', i === selected);
+    });
+    renderCaption();
+}
+
+async function commit() {
+    holding = false;
+    const tab = tabs[selected];
+    await hide();
+    if (!tab) return;
+    try {
+        await invoke('switch_tab', { tabId: tab.id });
+    } catch (error) {
+        console.error('Failed to switch tab:', error);
+    }
+}
+
+async function hide() {
+    holding = false;
+    try {
+        await invoke('hide_tab_switcher');
+    } catch (error) {
+        console.error('Failed to hide tab switcher:', error);
+    }
+}
+
+function timeAgo(timestamp) {
+    if (!timestamp) return '';
+    const seconds = Math.max(0, Math.round((Date.now() - timestamp) / 1000));
+    if (seconds < 60) return 'just now';
+    if (seconds < 3600) return `${Math.floor(seconds / 60)} min ago`;
+    if (seconds < 86400) return `${Math.floor(seconds / 3600)} h ago`;
+    return `${Math.floor(seconds / 86400)} d ago`;
+}
+
+function escapeHtml(str) {
+    const div = document.createElement('div');
+    div.textContent = str;
+    return div.innerHTML;
+}
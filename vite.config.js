@@ -1,4 +1,8 @@
 import { defineConfig } from 'vite';
+import { dirname, resolve } from 'path';
+import { fileURLToPath } from 'url';
+
+const root = resolve(dirname(fileURLToPath(import.meta.url)), 'src');
 
 export default defineConfig({
     root: 'src',
@@ -9,6 +13,17 @@ export default defineConfig({
     build: {
         outDir: '../dist',
         emptyOutDir: true,
+        // Every page loaded by a window, not just index.html
+        rollupOptions: {
+            input: {
+                main: resolve(root, 'index.html'),
+                notes: resolve(root, 'notes.html'),
+                palette: resolve(root, 'command-palette.html'),
+                switcher: resolve(root, 'tab-switcher.html'),
+                clipboard: resolve(root, 'clipboard-history.html'),
+                lock: resolve(root, 'lock.html'),
+            },
+        },
     },
     clearScreen: false,
 });